@@ -1,5 +1,6 @@
 pub mod leaderboard;
 pub mod management;
+pub mod master;
 pub mod player_profile;
 pub mod player_stats;
 pub mod trivia;
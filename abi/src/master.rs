@@ -0,0 +1,57 @@
+use async_graphql::{Request, Response};
+use linera_sdk::abi::{ContractAbi, ServiceAbi};
+use linera_sdk::graphql::GraphQLMutationRoot;
+use linera_sdk::linera_base_types::{AccountOwner, Amount};
+use serde::{Deserialize, Serialize};
+
+use crate::trivia::{QuestionError, QuestionFilter, QuestionInput};
+
+/// Lives here rather than in master so trivia can call master; master already depends on trivia
+#[derive(Debug, Deserialize, Serialize)]
+pub struct MasterAbi;
+
+impl ContractAbi for MasterAbi {
+    type Operation = MasterOperation;
+    type Response = MasterResponse;
+}
+
+impl ServiceAbi for MasterAbi {
+    type Query = Request;
+    type QueryResponse = Response;
+}
+
+#[derive(Debug, Deserialize, Serialize, GraphQLMutationRoot)]
+pub enum MasterOperation {
+    // Question bank management, admin only
+    AddQuestions { questions: Vec<QuestionInput> },
+    UpdateQuestion { id: u64, question: QuestionInput },
+    RetireQuestion { id: u64 },
+    // Moves questions out of the old single-register bank, `batch` at a time
+    MigrateQuestionBank { batch: u32 },
+    AddModerator { owner: AccountOwner },
+    RemoveModerator { owner: AccountOwner },
+
+    // Community submissions, admin or moderators
+    ApproveSubmission { id: u64 },
+    RejectSubmission { id: u64, reason: String },
+    EditSubmission { id: u64, question: QuestionInput },
+
+    // Trivia on the player's chain, for its signer; forwarded to master's own chain
+    SubmitQuestion { question: QuestionInput },
+
+    // Trivia on a play chain, during a game; forwarded to master's own chain, and
+    // the questions come back through master on the play chain
+    RequestQuestions { game_id: u64, count: u8, filter: QuestionFilter },
+    SendProtocolFee { amount: Amount },
+}
+
+/// Outcome for one question: its ID, or why it was refused
+pub type QuestionResult = Result<u64, QuestionError>;
+
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub enum MasterResponse {
+    #[default]
+    Ok,
+    Questions(Vec<QuestionResult>),
+    Migrated { left: u64 },
+}
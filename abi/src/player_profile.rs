@@ -28,4 +28,62 @@ pub struct PlayerProfile {
 
     /// Total points scored across all games
     pub total_score: u64,
-}
\ No newline at end of file
+
+    /// Community questions accepted into the master bank
+    pub questions_approved: u64,
+}
+
+/// Wins needed for each tier, and the reward multiplier it brings
+const TIERS: [(u64, &str, u32); 4] = [
+    (0, "Bronze", 100),
+    (10, "Silver", 110),
+    (50, "Gold", 125),
+    (200, "Diamond", 150),
+];
+
+impl PlayerProfile {
+    /// Adds a finished game; tier and reward multiplier follow the number of wins.
+    /// Only games with something staked count as wins, so free games can't raise the tier
+    pub fn record_game(&mut self, score: u64, won: bool, winnings: Amount, pot: Amount) {
+        self.games_played += 1;
+        self.wins += u64::from(won && !pot.is_zero());
+        self.total_score = self.total_score.saturating_add(score);
+        self.lifetime_winnings.saturating_add_assign(winnings);
+
+        let (_, tier, multiplier) = TIERS.iter()
+            .rev()
+            .find(|(wins, _, _)| self.wins >= *wins)
+            .expect("Bronze needs no wins");
+        self.tier = tier.to_string();
+        self.reward_multiplier = *multiplier;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn wins_raise_tier_and_multiplier() {
+        let mut profile = PlayerProfile::default();
+        profile.record_game(40, false, Amount::ZERO, Amount::from_tokens(2));
+        assert_eq!((profile.tier.as_str(), profile.reward_multiplier), ("Bronze", 100));
+
+        for _ in 0..10 {
+            profile.record_game(100, true, Amount::ONE, Amount::from_tokens(2));
+        }
+        assert_eq!((profile.tier.as_str(), profile.reward_multiplier), ("Silver", 110));
+        assert_eq!((profile.games_played, profile.wins, profile.total_score), (11, 10, 1040));
+        assert_eq!(profile.lifetime_winnings, Amount::from_tokens(10));
+    }
+
+    #[test]
+    fn wins_without_a_pot_do_not_count() {
+        let mut profile = PlayerProfile::default();
+        for _ in 0..10 {
+            profile.record_game(100, true, Amount::ZERO, Amount::ZERO);
+        }
+        assert_eq!((profile.tier.as_str(), profile.reward_multiplier), ("Bronze", 100));
+        assert_eq!((profile.games_played, profile.wins), (10, 0));
+    }
+}
@@ -1,9 +1,11 @@
-use linera_sdk::linera_base_types::AccountOwner;
+use linera_sdk::linera_base_types::{AccountOwner, Amount};
 use serde::{Deserialize, Serialize};
-use async_graphql::{InputObject, SimpleObject};
+use async_graphql::{Enum, InputObject, SimpleObject};
+use thiserror::Error;
 
 /// Question struct for the trivia game
-#[derive(Debug, Clone, Serialize, Deserialize, SimpleObject, PartialEq, Eq)]
+#[derive(Debug, Clone, Serialize, Deserialize, SimpleObject, InputObject, PartialEq, Eq)]
+#[graphql(input_name = "QuestionRecordInput")]
 pub struct Question {
     pub id: u64,
     pub text: String,
@@ -13,8 +15,10 @@ pub struct Question {
     pub difficulty: u8,
 }
 
-/// Input version of Question for GraphQL mutations (AddQuestions)
-#[derive(Debug, Clone, Serialize, Deserialize, InputObject)]
+/// Input version of Question for GraphQL mutations (AddQuestions);
+/// shown as `QuestionDraft` while a community submission awaits review
+#[derive(Debug, Clone, Serialize, Deserialize, SimpleObject, InputObject, PartialEq, Eq)]
+#[graphql(name = "QuestionDraft", input_name = "QuestionInput")]
 pub struct QuestionInput {
     pub text: String,
     pub choices: Vec<String>,
@@ -23,6 +27,156 @@ pub struct QuestionInput {
     pub difficulty: u8,
 }
 
+/// Which questions a room wants from master
+#[derive(Debug, Clone, Default, Serialize, Deserialize, SimpleObject, InputObject, PartialEq, Eq)]
+#[graphql(input_name = "QuestionFilterInput")]
+pub struct QuestionFilter {
+    /// Any of these categories; empty means all
+    pub categories: Vec<String>,
+    pub difficulty: DifficultyProfile,
+}
+
+impl QuestionFilter {
+    pub const MAX_CATEGORIES: usize = 10;
+
+    pub fn is_valid(&self) -> bool {
+        self.categories.len() <= Self::MAX_CATEGORIES && self.difficulty.is_valid()
+    }
+}
+
+/// Difficulty wanted for each question of a game
+#[derive(Debug, Clone, Default, Serialize, Deserialize, SimpleObject, InputObject, PartialEq, Eq)]
+#[graphql(input_name = "DifficultyProfileInput")]
+pub struct DifficultyProfile {
+    pub kind: DifficultyKind,
+    /// Level for `Fixed`, first question's level for `Ramp`
+    pub min: u8,
+    /// Last question's level for `Ramp`
+    pub max: u8,
+}
+
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, Enum, PartialEq, Eq)]
+pub enum DifficultyKind {
+    #[default]
+    Any,
+    Fixed,
+    /// Easy to hard across the game
+    Ramp,
+}
+
+impl DifficultyProfile {
+    pub fn is_valid(&self) -> bool {
+        match self.kind {
+            DifficultyKind::Any => true,
+            DifficultyKind::Fixed => (1..=5).contains(&self.min),
+            DifficultyKind::Ramp => 1 <= self.min && self.min <= self.max && self.max <= 5,
+        }
+    }
+
+    /// Level wanted for question `index` of `count`; a lone question is the hardest
+    pub fn target(&self, index: usize, count: usize) -> Option<u8> {
+        match self.kind {
+            DifficultyKind::Any => None,
+            DifficultyKind::Fixed => Some(self.min),
+            DifficultyKind::Ramp if count <= 1 => Some(self.max),
+            DifficultyKind::Ramp => {
+                let span = (self.max - self.min) as usize;
+                Some(self.min + (span * index / (count - 1)) as u8)
+            }
+        }
+    }
+}
+
+/// Why a question was not accepted into the bank
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Error)]
+pub enum QuestionError {
+    #[error("Question text is empty")]
+    EmptyText,
+
+    #[error("Questions need 2 to 6 choices, got {0}")]
+    ChoiceCount(usize),
+
+    #[error("Choice {0} is empty")]
+    EmptyChoice(usize),
+
+    #[error("Choice \"{0}\" appears more than once")]
+    DuplicateChoice(String),
+
+    #[error("correct_idx {0} is not one of the choices")]
+    CorrectIndex(u8),
+
+    #[error("Difficulty must be 1 to 5, got {0}")]
+    Difficulty(u8),
+
+    #[error("Question {0} does not exist")]
+    UnknownQuestion(u64),
+
+    #[error("Question {0} is retired")]
+    Retired(u64),
+}
+
+impl QuestionInput {
+    pub const MIN_CHOICES: usize = 2;
+    pub const MAX_CHOICES: usize = 6;
+
+    /// Checks the rules every question in the bank must follow
+    pub fn validate(&self) -> Result<(), QuestionError> {
+        if self.text.trim().is_empty() {
+            return Err(QuestionError::EmptyText);
+        }
+
+        let count = self.choices.len();
+        if !(Self::MIN_CHOICES..=Self::MAX_CHOICES).contains(&count) {
+            return Err(QuestionError::ChoiceCount(count));
+        }
+
+        for (i, choice) in self.choices.iter().enumerate() {
+            let choice = choice.trim();
+            if choice.is_empty() {
+                return Err(QuestionError::EmptyChoice(i));
+            }
+            if self.choices[..i].iter().any(|c| c.trim().eq_ignore_ascii_case(choice)) {
+                return Err(QuestionError::DuplicateChoice(choice.to_string()));
+            }
+        }
+
+        if self.correct_idx as usize >= count {
+            return Err(QuestionError::CorrectIndex(self.correct_idx));
+        }
+
+        if !(1..=5).contains(&self.difficulty) {
+            return Err(QuestionError::Difficulty(self.difficulty));
+        }
+
+        Ok(())
+    }
+
+    pub fn into_question(self, id: u64) -> Question {
+        Question {
+            id,
+            text: self.text,
+            choices: self.choices,
+            correct_idx: self.correct_idx,
+            category: self.category,
+            difficulty: self.difficulty,
+        }
+    }
+}
+
+impl Question {
+    /// Same rules as `QuestionInput::validate`; questions stored before those rules may break them
+    pub fn validate(&self) -> Result<(), QuestionError> {
+        QuestionInput {
+            text: self.text.clone(),
+            choices: self.choices.clone(),
+            correct_idx: self.correct_idx,
+            category: self.category.clone(),
+            difficulty: self.difficulty,
+        }
+        .validate()
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, SimpleObject, PartialEq, Eq)]
 pub struct TriviaGame {
     pub room_name: String,
@@ -39,9 +193,64 @@ pub struct PlayerScore {
     pub score: u64,
 }
 
+/// Amount paid to one player at the end of a game
+#[derive(Debug, Clone, Serialize, Deserialize, SimpleObject, InputObject, PartialEq, Eq)]
+#[graphql(input_name = "PayoutInput")]
+pub struct Payout {
+    pub player: AccountOwner,
+    pub amount: Amount,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, SimpleObject, PartialEq, Eq)]
 pub struct UserStatus {
     pub wins: u64,
     pub losses: u64,
     pub total_score: u64,
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn profile(kind: DifficultyKind, min: u8, max: u8) -> DifficultyProfile {
+        DifficultyProfile { kind, min, max }
+    }
+
+    #[test]
+    fn any_and_fixed_targets() {
+        assert_eq!(profile(DifficultyKind::Any, 0, 0).target(3, 10), None);
+
+        let fixed = profile(DifficultyKind::Fixed, 4, 0);
+        assert!((0..10).all(|i| fixed.target(i, 10) == Some(4)));
+    }
+
+    #[test]
+    fn ramp_runs_from_min_to_max() {
+        let ramp = profile(DifficultyKind::Ramp, 1, 5);
+        let targets: Vec<u8> = (0..10).map(|i| ramp.target(i, 10).unwrap()).collect();
+
+        assert_eq!(targets, [1, 1, 1, 2, 2, 3, 3, 4, 4, 5]);
+    }
+
+    #[test]
+    fn short_and_flat_ramps() {
+        let ramp = profile(DifficultyKind::Ramp, 2, 4);
+        assert_eq!(ramp.target(0, 1), Some(4));
+        assert_eq!(ramp.target(0, 0), Some(4));
+        assert_eq!([ramp.target(0, 2), ramp.target(1, 2)], [Some(2), Some(4)]);
+
+        let flat = profile(DifficultyKind::Ramp, 3, 3);
+        assert!((0..5).all(|i| flat.target(i, 5) == Some(3)));
+    }
+
+    #[test]
+    fn profiles_out_of_range_are_invalid() {
+        assert!(profile(DifficultyKind::Any, 9, 0).is_valid());
+        assert!(!profile(DifficultyKind::Fixed, 0, 0).is_valid());
+        assert!(!profile(DifficultyKind::Fixed, 6, 0).is_valid());
+        assert!(!profile(DifficultyKind::Ramp, 4, 2).is_valid());
+        assert!(!profile(DifficultyKind::Ramp, 0, 5).is_valid());
+        assert!(!profile(DifficultyKind::Ramp, 1, 6).is_valid());
+        assert!(profile(DifficultyKind::Ramp, 1, 5).is_valid());
+    }
+}
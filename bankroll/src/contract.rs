@@ -6,10 +6,10 @@ use async_trait::async_trait;
 use linera_sdk::{
     views::View,
     Contract, ContractRuntime,
-    linera_base_types::{AccountOwner, Amount, ChainId, Timestamp},
+    linera_base_types::{Account, AccountOwner, Amount, ChainId, TimeDelta},
 };
-use state::BankrollState;
-use crate::{BankrollOperation, BankrollMessage, BankrollResponse, BankrollParameters};
+use state::{BankrollState, DebtRecord, DebtStatus, EscrowKey, LedgerKind, Link};
+use crate::{BankrollError, BankrollOperation, BankrollMessage, BankrollResponse, BankrollParameters};
 use bcs;
 
 pub struct BankrollContract {
@@ -42,96 +42,516 @@ impl Contract for BankrollContract {
     }
 
     async fn execute_operation(&mut self, operation: BankrollOperation) -> BankrollResponse {
+        let access = match operation {
+            BankrollOperation::Balance { .. }
+            | BankrollOperation::ClaimDailyBonus {}
+            | BankrollOperation::Deposit { .. }
+            | BankrollOperation::Withdraw { .. }
+            | BankrollOperation::ExpireDebts {}
+            | BankrollOperation::ReportTreasury {}
+            | BankrollOperation::CheckSolvency {} => Ok(()),
+            BankrollOperation::FundReserve { .. } => self.check_admin(),
+            // Owners can move their own funds; apps like trivia move players' stakes
+            BankrollOperation::SendFunds { owner, .. }
+                if self.runtime.authenticated_caller_id().is_none()
+                    && self.runtime.authenticated_signer() == Some(owner) => Ok(()),
+            _ => self.check_trusted_caller(),
+        };
+        if let Err(error) = access {
+            panic!("{error}");
+        }
+
         match operation {
             BankrollOperation::Balance { owner } => {
-                let balance = self.state.accounts.get(&owner).await
-                    .expect("Failed to read balance")
-                    .unwrap_or(Amount::ZERO);
+                BankrollResponse::Balance(self.state.balance_of(owner).await)
+            }
 
-                // Claim daily bonus if eligible
-                let mut daily = self.state.daily_bonus.get_mut();
-                let current_time = self.runtime.system_time();
-                let bonus_amount = daily.claim_bonus(current_time, self.runtime.application_parameters().bonus);
+            // Claims are recorded on master, so each day can be claimed once whichever chain asks
+            BankrollOperation::ClaimDailyBonus {} => {
+                let owner = self.signer();
+                let master = self.runtime.application_parameters().master_chain;
+                if self.runtime.chain_id() != master {
+                    self.send_message(master, BankrollMessage::DailyBonusRequest { owner });
+                    return BankrollResponse::Ok;
+                }
 
-                if !bonus_amount.is_zero() {
-                    let new_balance = balance.saturating_add(bonus_amount);
-                    self.state.accounts.insert(owner, new_balance).unwrap();
-                    return BankrollResponse::Balance(new_balance);
+                match self.claim_daily_bonus(owner, master).await {
+                    Ok(paid) => BankrollResponse::Bonus(paid),
+                    Err(error) => panic!("{error}"),
                 }
+            }
+
+            // The native transfer and the ledger entry succeed or fail together
+            BankrollOperation::Deposit { amount } => {
+                let owner = self.signer();
+                let vault = self.vault();
+                self.runtime.transfer(owner, vault, amount);
+
+                match self.credit(owner, amount, LedgerKind::Deposit, Link::default()).await {
+                    Ok(balance) => BankrollResponse::Balance(balance),
+                    Err(error) => panic!("{error}"),
+                }
+            }
 
+            BankrollOperation::Withdraw { amount } => {
+                let owner = self.signer();
+                let balance = match self.debit(owner, amount, LedgerKind::Withdraw, Link::default()).await {
+                    Ok(balance) => balance,
+                    Err(error) => panic!("{error}"),
+                };
+
+                let vault = self.vault();
+                let destination = Account {
+                    chain_id: vault.chain_id,
+                    owner,
+                };
+                self.runtime.transfer(vault.owner, destination, amount);
                 BankrollResponse::Balance(balance)
             }
 
-            BankrollOperation::UpdateBalance { owner, amount } => {
-                // Only allow trusted apps (like trivia) or master to update
-                // In production: add auth check
-                self.state.accounts.insert(owner, amount).unwrap_or_else(|_| panic!("Insert failed"));
-                BankrollResponse::Ok
+            BankrollOperation::Credit { owner, amount } => {
+                let result = match self.state.draw_reserve(amount) {
+                    Ok(()) => self.credit(owner, amount, LedgerKind::Adjustment, Link::default()).await,
+                    Err(error) => Err(error),
+                };
+                match result {
+                    Ok(balance) => BankrollResponse::Balance(balance),
+                    Err(error) => panic!("{error}"),
+                }
             }
 
-            BankrollOperation::NotifyDebt { amount, target_chain } => {
-                let debt_id = self.state.next_debt_id.get();
-                self.state.next_debt_id.set(debt_id + 1);
+            BankrollOperation::Debit { owner, amount } => {
+                match self.debit(owner, amount, LedgerKind::Adjustment, Link::default()).await {
+                    Ok(balance) => {
+                        self.state.bonus_reserve.get_mut().saturating_add_assign(amount);
+                        BankrollResponse::Balance(balance)
+                    }
+                    Err(error) => panic!("{error}"),
+                }
+            }
 
-                let debt_record = DebtRecord {
-                    id: debt_id,
-                    amount,
-                    target_chain,
-                    created_at: self.runtime.system_time(),
-                    status: DebtStatus::Pending,
+            BankrollOperation::Transfer { from, to, amount } => {
+                // A failed credit panics, which also rolls back the debit
+                let result = match self.debit(from, amount, LedgerKind::Transfer, Link::with(to)).await {
+                    Ok(_) => self.credit(to, amount, LedgerKind::Transfer, Link::with(from)).await,
+                    Err(error) => Err(error),
                 };
+                if let Err(error) = result {
+                    panic!("{error}");
+                }
+                BankrollResponse::Ok
+            }
 
-                self.state.debt_log.insert(debt_id, debt_record).unwrap();
+            // The ledger entry here and the credit there travel with the tokens
+            BankrollOperation::SendFunds { owner, amount, target_chain } => {
+                if let Err(error) = self.debit(owner, amount, LedgerKind::ChainTransfer, Link::default()).await {
+                    panic!("{error}");
+                }
 
-                // Notify target chain
-                self.send_message(target_chain, BankrollMessage::DebtNotif {
-                    debt_id,
-                    amount,
-                    created_at: self.runtime.system_time(),
-                });
+                self.send_tokens(target_chain, amount);
+                self.send_message(target_chain, BankrollMessage::Funds { owner, amount });
+                BankrollResponse::Ok
+            }
 
+            BankrollOperation::NotifyDebt { debtor, creditor, amount, target_chain } => {
+                self.notify_debt(debtor, creditor, amount, target_chain, None);
                 BankrollResponse::Ok
             }
 
+            BankrollOperation::ExpireDebts {} => {
+                let now = self.runtime.system_time();
+                let mut overdue = Vec::new();
+                self.state.pending_debts.for_each_index_while(|key| {
+                    if DebtRecord::due_micros_of_key(key) > now.micros() {
+                        return Ok(false);
+                    }
+                    overdue.push(DebtRecord::id_of_due_key(key));
+                    Ok(true)
+                }).await.expect("Failed to read pending debts");
+
+                // A penalty debt that expires too is not penalized again.
+                // Callers decide what else to do with the debtors, e.g. free their seats
+                let percent = self.runtime.application_parameters().late_penalty_percent;
+                let mut debtors = Vec::new();
+                for id in overdue {
+                    let mut debt = self.state.debt_log.get(&id).await
+                        .expect("Failed to read debt")
+                        .unwrap_or_else(|| panic!("{}", BankrollError::UnknownDebt(id)));
+                    self.close_debt(&debt);
+                    debt.status = DebtStatus::Expired;
+                    debt.settled_at = Some(now);
+                    debtors.push(debt.debtor);
+
+                    let penalty = debt.amount.saturating_mul(percent as u128).saturating_div(100);
+                    if debt.penalty_for.is_none() && !penalty.is_zero() {
+                        self.notify_debt(debt.debtor, debt.creditor, penalty, debt.target_chain, Some(id));
+                    }
+                    self.state.debt_log.insert(&id, debt).expect("Failed to store debt");
+                }
+                BankrollResponse::Expired(debtors)
+            }
+
             BankrollOperation::TransferPot { amount, target_chain } => {
-                // Add to pot and notify
-                let pot_id = self.state.next_pot_id.get();
-                self.state.next_pot_id.set(pot_id + 1);
-
-                let pot_record = TokenPotRecord {
-                    id: pot_id,
-                    amount,
-                    target_chain,
-                    created_at: self.runtime.system_time(),
-                };
-                self.state.token_pot_log.insert(pot_id, pot_record).unwrap();
+                if let Err(error) = self.state.draw_reserve(amount) {
+                    panic!("{error}");
+                }
+                self.transfer_pot(amount, target_chain).await;
+                BankrollResponse::Ok
+            }
 
-                self.send_message(target_chain, BankrollMessage::TokenPot { amount });
+            BankrollOperation::FundReserve { amount } => {
+                let admin = self.signer();
+                let vault = self.vault();
+                self.runtime.transfer(admin, vault, amount);
 
+                self.state.fund_reserve(amount);
                 BankrollResponse::Ok
             }
+
+            BankrollOperation::PayBonus { owner, amount, target_chain } => {
+                self.request_bonus(owner, amount, target_chain).await;
+                BankrollResponse::Ok
+            }
+
+            // Any player short of funds panics and fails the whole start
+            BankrollOperation::LockStakes { game_id, players, stake } => {
+                let key = self.escrow_key(game_id);
+                let now = self.runtime.system_time();
+                if let Err(error) = self.state.lock_stakes(key, players, stake, now).await {
+                    panic!("{error}");
+                }
+                BankrollResponse::Ok
+            }
+
+            BankrollOperation::ReleaseEscrow { game_id, payouts, fee } => {
+                let key = self.escrow_key(game_id);
+                let now = self.runtime.system_time();
+                let master = self.runtime.application_parameters().master_chain;
+                if let Err(error) = self.state.release_escrow(&key, payouts, fee, master, now).await {
+                    panic!("{error}");
+                }
+
+                if !fee.is_zero() {
+                    self.send_pot(fee, master);
+                }
+                BankrollResponse::Ok
+            }
+
+            BankrollOperation::RefundEscrow { game_id } => {
+                let key = self.escrow_key(game_id);
+                let now = self.runtime.system_time();
+                if let Err(error) = self.state.refund_escrow(&key, now).await {
+                    panic!("{error}");
+                }
+                BankrollResponse::Ok
+            }
+
+            // Tell master what this chain actually holds
+            BankrollOperation::ReportTreasury {} => {
+                let vault = self.vault();
+                let amount = self.runtime.owner_balance(vault.owner);
+                let master = self.runtime.application_parameters().master_chain;
+                self.send_message(master, BankrollMessage::TokenUpdate { amount });
+                BankrollResponse::Ok
+            }
+
+            BankrollOperation::CheckSolvency {} => {
+                let vault = self.vault();
+                let native = self.runtime.owner_balance(vault.owner);
+                BankrollResponse::Solvency(self.state.solvency(native).await)
+            }
         }
     }
 
     async fn execute_message(&mut self, message: BankrollMessage) {
         match message {
+            // Target side: pay from the debtor's balance here. A late penalty arrives as a debt of its own
+            BankrollMessage::DebtNotif { debt_id, debtor, amount } => {
+                let origin = self.runtime.message_origin_chain_id().expect("Origin chain required");
+                let now = self.runtime.system_time();
+
+                let reply = match self.debit(debtor, amount, LedgerKind::Debt, Link::debt(debt_id)).await {
+                    Ok(_) => {
+                        self.send_tokens(origin, amount);
+                        BankrollMessage::DebtPaid { debt_id, amount, paid_at: now }
+                    }
+                    Err(error) => BankrollMessage::DebtRejected { debt_id, reason: error.to_string() },
+                };
+                self.send_message(origin, reply);
+            }
+
+            // Origin side: a late payment still settles an expired debt
             BankrollMessage::DebtPaid { debt_id, amount, paid_at } => {
-                if let Some(mut debt) = self.state.debt_log.get(&debt_id).await.unwrap() {
-                    debt.status = DebtStatus::Paid;
-                    debt.paid_at = Some(paid_at);
-                    self.state.debt_log.insert(debt_id, debt).unwrap();
+                let mut debt = self.settled_debt(debt_id).await;
+                if debt.status == DebtStatus::Pending {
+                    self.close_debt(&debt);
+                }
+                let link = Link { counterparty: Some(debt.debtor), ..Link::debt(debt_id) };
+                if let Err(error) = self.credit(debt.creditor, amount, LedgerKind::Debt, link).await {
+                    panic!("{error}");
+                }
+
+                debt.status = DebtStatus::Paid;
+                debt.settled_at = Some(paid_at);
+                debt.paid = amount;
+                self.state.debt_log.insert(&debt_id, debt).expect("Failed to store debt");
+            }
+
+            BankrollMessage::DebtRejected { debt_id, reason } => {
+                let mut debt = self.settled_debt(debt_id).await;
+                if debt.status == DebtStatus::Pending {
+                    self.close_debt(&debt);
+                    debt.status = DebtStatus::Rejected;
+                    debt.settled_at = Some(self.runtime.system_time());
                 }
+                debt.reason = Some(reason);
+                self.state.debt_log.insert(&debt_id, debt).expect("Failed to store debt");
             }
+
             BankrollMessage::TokenUpdate { amount } => {
-                let origin = self.runtime.message_sender().expect("Sender required");
-                self.state.public_balances.insert(origin, amount).unwrap();
+                let origin = self.runtime.message_origin_chain_id().expect("Origin chain required");
+                self.state.public_balances.insert(&origin, amount).expect("Failed to store balance");
+            }
+
+            BankrollMessage::TokenPot { amount } => self.state.receive_pot(amount),
+
+            BankrollMessage::Funds { owner, amount } => {
+                if let Err(error) = self.credit(owner, amount, LedgerKind::ChainTransfer, Link::default()).await {
+                    panic!("{error}");
+                }
+            }
+
+            // Master: only bankrolls send these, and only for bonuses they granted
+            BankrollMessage::BonusRequest { owner, amount, target_chain } => {
+                self.pay_bonus(owner, amount, target_chain).await;
+            }
+
+            // Master: a claim made on another chain, paid there. A refused claim is only logged
+            BankrollMessage::DailyBonusRequest { owner } => {
+                let origin = self.runtime.message_origin_chain_id().expect("Origin chain required");
+                if let Err(error) = self.claim_daily_bonus(owner, origin).await {
+                    log::info!("Daily bonus for {owner} not paid: {error}");
+                }
+            }
+
+            BankrollMessage::DailyBonusClaimed { owner, bonus } => {
+                let master = self.runtime.application_parameters().master_chain;
+                if self.runtime.message_origin_chain_id() != Some(master) {
+                    return;
+                }
+                self.state.daily_bonus.insert(&owner, bonus).expect("Failed to store daily bonus");
+            }
+
+            BankrollMessage::Bonus { owner, amount } => {
+                let master = self.runtime.application_parameters().master_chain;
+                if self.runtime.message_origin_chain_id() != Some(master) {
+                    return;
+                }
+                let now = self.runtime.system_time();
+                if let Err(error) = self.state.receive_bonus(owner, amount, now).await {
+                    panic!("{error}");
+                }
             }
-            _ => {}
         }
     }
 }
 
 impl BankrollContract {
+    fn check_admin(&mut self) -> Result<(), BankrollError> {
+        match self.runtime.authenticated_signer() {
+            Some(signer) if signer == self.runtime.application_parameters().admin => Ok(()),
+            Some(_) => Err(BankrollError::NotAdmin),
+            None => Err(BankrollError::Unauthenticated),
+        }
+    }
+
+    // Add to pot and notify; the caller has already taken `amount` out of the reserve
+    async fn transfer_pot(&mut self, amount: Amount, target_chain: ChainId) {
+        let now = self.runtime.system_time();
+        self.state.record_pot(amount, target_chain, now).await;
+        self.send_pot(amount, target_chain);
+    }
+
+    // The tokens for a pot already booked, and the message that adds them to target_chain's reserve
+    fn send_pot(&mut self, amount: Amount, target_chain: ChainId) {
+        self.send_tokens(target_chain, amount);
+        self.send_message(target_chain, BankrollMessage::TokenPot { amount });
+    }
+
+    // Bonuses are paid by master, whose reserve collects every game's fee
+    async fn request_bonus(&mut self, owner: AccountOwner, amount: Amount, target_chain: ChainId) {
+        let master = self.runtime.application_parameters().master_chain;
+        if self.runtime.chain_id() == master {
+            self.pay_bonus(owner, amount, target_chain).await;
+        } else {
+            self.send_message(master, BankrollMessage::BonusRequest { owner, amount, target_chain });
+        }
+    }
+
+    // Master: records today's claim and pays it to the owner on target_chain.
+    // An empty reserve pays nothing, so the day is left unclaimed
+    async fn claim_daily_bonus(&mut self, owner: AccountOwner, target_chain: ChainId) -> Result<Amount, BankrollError> {
+        let now = self.runtime.system_time();
+        let base = self.runtime.application_parameters().bonus;
+        let bonus = self.state.daily_bonus.get_mut_or_default(&owner).await
+            .expect("Failed to read daily bonus");
+
+        let mut claimed = bonus.clone();
+        let amount = claimed.claim(now, base).ok_or(BankrollError::BonusAlreadyClaimed)?;
+        let reserve = *self.state.bonus_reserve.get();
+        if reserve.is_zero() {
+            return Err(BankrollError::InsufficientReserve { reserve, amount });
+        }
+        *bonus = claimed.clone();
+
+        let paid = self.pay_bonus(owner, amount, target_chain).await;
+        if target_chain != self.runtime.chain_id() {
+            self.send_message(target_chain, BankrollMessage::DailyBonusClaimed { owner, bonus: claimed });
+        }
+        Ok(paid)
+    }
+
+    // Never pays out more bonus than the reserve holds; returns what was paid
+    async fn pay_bonus(&mut self, owner: AccountOwner, amount: Amount, target_chain: ChainId) -> Amount {
+        let now = self.runtime.system_time();
+        let local = target_chain == self.runtime.chain_id();
+        let paid = match self.state.pay_bonus(owner, amount, local, now).await {
+            Ok(paid) => paid,
+            Err(error) => panic!("{error}"),
+        };
+
+        if !local && !paid.is_zero() {
+            self.send_tokens(target_chain, paid);
+            self.send_message(target_chain, BankrollMessage::Bonus { owner, amount: paid });
+        }
+        paid
+    }
+
+    // Native tokens to the bankroll on another chain, backing what a message moves there
+    fn send_tokens(&mut self, target_chain: ChainId, amount: Amount) {
+        let vault = self.vault();
+        let destination = Account {
+            chain_id: target_chain,
+            owner: vault.owner,
+        };
+        self.runtime.transfer(vault.owner, destination, amount);
+    }
+
+    fn signer(&mut self) -> AccountOwner {
+        match self.runtime.authenticated_signer() {
+            Some(owner) => owner,
+            None => panic!("{}", BankrollError::Unauthenticated),
+        }
+    }
+
+    // Native tokens backing the ledger sit in the application's own account
+    fn vault(&mut self) -> Account {
+        Account {
+            chain_id: self.runtime.chain_id(),
+            owner: self.runtime.application_id().forget_abi().into(),
+        }
+    }
+
+    // Records the debt and asks the debtor's bankroll on target_chain to pay it
+    fn notify_debt(
+        &mut self,
+        debtor: AccountOwner,
+        creditor: AccountOwner,
+        amount: Amount,
+        target_chain: ChainId,
+        penalty_for: Option<u64>,
+    ) {
+        let debt_id = *self.state.next_debt_id.get();
+        self.state.next_debt_id.set(debt_id + 1);
+
+        let now = self.runtime.system_time();
+        let timeout = self.runtime.application_parameters().debt_timeout_seconds;
+        let debt_record = DebtRecord {
+            id: debt_id,
+            debtor,
+            creditor,
+            amount,
+            target_chain,
+            created_at: now,
+            due_at: now.saturating_add(TimeDelta::from_secs(timeout)),
+            status: DebtStatus::Pending,
+            settled_at: None,
+            paid: Amount::ZERO,
+            reason: None,
+            penalty_for,
+        };
+        self.state.pending_debts.insert(&debt_record.due_key(), ()).expect("Failed to store pending debt");
+        self.state.debt_log.insert(&debt_id, debt_record).expect("Failed to store debt");
+        self.state.treasury.get_mut().outstanding_debts.saturating_add_assign(amount);
+
+        self.send_message(target_chain, BankrollMessage::DebtNotif { debt_id, debtor, amount });
+    }
+
+    // A pending debt stops counting as outstanding once it is settled or expires
+    fn close_debt(&mut self, debt: &DebtRecord) {
+        self.state.pending_debts.remove(&debt.due_key()).expect("Failed to remove pending debt");
+        let treasury = self.state.treasury.get_mut();
+        treasury.outstanding_debts = treasury.outstanding_debts.saturating_sub(debt.amount);
+    }
+
+    // Only the chain a debt was sent to may settle it
+    async fn settled_debt(&mut self, debt_id: u64) -> DebtRecord {
+        let debt = self.state.debt_log.get(&debt_id).await
+            .expect("Failed to read debt")
+            .unwrap_or_else(|| panic!("{}", BankrollError::UnknownDebt(debt_id)));
+        if self.runtime.message_origin_chain_id() != Some(debt.target_chain) {
+            panic!("{}", BankrollError::WrongDebtChain(debt_id));
+        }
+        debt
+    }
+
+    fn escrow_key(&mut self, game_id: u64) -> EscrowKey {
+        EscrowKey {
+            play_chain: self.runtime.chain_id(),
+            game_id,
+        }
+    }
+
+    async fn credit(
+        &mut self,
+        owner: AccountOwner,
+        amount: Amount,
+        kind: LedgerKind,
+        link: Link,
+    ) -> Result<Amount, BankrollError> {
+        let now = self.runtime.system_time();
+        self.state.credit(owner, amount, kind, link, now).await
+    }
+
+    async fn debit(
+        &mut self,
+        owner: AccountOwner,
+        amount: Amount,
+        kind: LedgerKind,
+        link: Link,
+    ) -> Result<Amount, BankrollError> {
+        let now = self.runtime.system_time();
+        self.state.debit(owner, amount, kind, link, now).await
+    }
+
+    // Applications from a trusted module (like trivia) or the admin directly
+    fn check_trusted_caller(&mut self) -> Result<(), BankrollError> {
+        match self.runtime.authenticated_caller_id() {
+            Some(caller) => {
+                let description = self.runtime.read_application_description(caller);
+                let params = self.runtime.application_parameters();
+                let trusted = description.creator_chain_id == params.master_chain
+                    && params.trusted_modules.contains(&description.module_id);
+                if trusted {
+                    Ok(())
+                } else {
+                    Err(BankrollError::UntrustedCaller(caller))
+                }
+            }
+            None => self.check_admin(),
+        }
+    }
+
     fn send_message(&mut self, destination: ChainId, message: BankrollMessage) {
         self.runtime.prepare_message(message).with_tracking().send_to(destination);
     }
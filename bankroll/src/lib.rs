@@ -1,10 +1,17 @@
-use async_graphql::{Request, Response};
-use linera_sdk::linera_base_types::{AccountOwner, Amount, ChainId, Timestamp};
+pub mod state;
+
+use async_graphql::{Request, Response, SimpleObject};
+use linera_sdk::linera_base_types::{AccountOwner, Amount, ApplicationId, ChainId, ModuleId, Timestamp};
 use linera_sdk::{
     graphql::GraphQLMutationRoot,
     linera_base_types::{ContractAbi, ServiceAbi},
 };
 use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use abi::trivia::Payout;
+
+use state::{DailyBonus, LedgerEntry};
 
 #[derive(Debug, Deserialize, Serialize)]
 pub struct BankrollAbi;
@@ -22,17 +29,48 @@ impl ServiceAbi for BankrollAbi {
 #[derive(Debug, Deserialize, Serialize, GraphQLMutationRoot)]
 pub enum BankrollOperation {
     Balance { owner: AccountOwner },
-    UpdateBalance { owner: AccountOwner, amount: Amount },
-    NotifyDebt { amount: Amount, target_chain: ChainId },
-    TransferPot { amount: Amount, target_chain: ChainId },
+    // Signer claims their own bonus; master keeps everyone's claims and pays from its reserve.
+    // Responds with the Bonus paid when run on master, otherwise Ok once the claim is sent
+    ClaimDailyBonus {},
+    Deposit { amount: Amount },  // Native tokens from the signer's account into bankroll
+    Withdraw { amount: Amount }, // Native tokens back to the signer's account on this chain
+    // Adjustments move funds between this chain's reserve and the owner; both fail if short
+    Credit { owner: AccountOwner, amount: Amount },
+    Debit { owner: AccountOwner, amount: Amount },
+    Transfer { from: AccountOwner, to: AccountOwner, amount: Amount },
+    // Moves the owner's balance, and the tokens backing it, to the bankroll on target_chain
+    SendFunds { owner: AccountOwner, amount: Amount, target_chain: ChainId },
+    // Ask the debtor's bankroll on target_chain to pay the creditor here
+    NotifyDebt { debtor: AccountOwner, creditor: AccountOwner, amount: Amount, target_chain: ChainId },
+    // Anyone may expire overdue debts; each is charged the late penalty as a new debt.
+    // Responds with the debtors whose debts just expired
+    ExpireDebts {},
+    ReportTreasury {}, // Sends this chain's native holdings to master
+    CheckSolvency {},  // Responds with a SolvencyReport; never fails on a discrepancy
+    TransferPot { amount: Amount, target_chain: ChainId }, // From this chain's reserve
+    FundReserve { amount: Amount }, // Native tokens from the admin's account into this chain's reserve
+    // Paid from master's reserve, capped by what it holds, to the owner on target_chain
+    PayBonus { owner: AccountOwner, amount: Amount, target_chain: ChainId },
+
+    // Game escrow on the calling play chain
+    LockStakes { game_id: u64, players: Vec<AccountOwner>, stake: Amount },
+    ReleaseEscrow { game_id: u64, payouts: Vec<Payout>, fee: Amount },
+    RefundEscrow { game_id: u64 },
 }
 
 #[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+// Messages that move a balance between chains are sent along with the native tokens backing it
 pub enum BankrollMessage {
-    TokenPot { amount: Amount },
-    DebtNotif { debt_id: u64, amount: Amount, created_at: Timestamp },
+    TokenPot { amount: Amount }, // Goes into the receiving chain's reserve
+    DebtNotif { debt_id: u64, debtor: AccountOwner, amount: Amount },
     DebtPaid { debt_id: u64, amount: Amount, paid_at: Timestamp },
+    DebtRejected { debt_id: u64, reason: String },
     TokenUpdate { amount: Amount },
+    Funds { owner: AccountOwner, amount: Amount }, // Sent with SendFunds; credits the owner here
+    BonusRequest { owner: AccountOwner, amount: Amount, target_chain: ChainId }, // To master
+    Bonus { owner: AccountOwner, amount: Amount }, // From master's reserve
+    DailyBonusRequest { owner: AccountOwner }, // To master, which records the claim
+    DailyBonusClaimed { owner: AccountOwner, bonus: DailyBonus }, // From master, kept here for queries
 }
 
 #[derive(Debug, Default, Deserialize, Serialize)]
@@ -40,10 +78,94 @@ pub enum BankrollResponse {
     #[default]
     Ok,
     Balance(Amount),
+    Bonus(Amount),
+    Expired(Vec<AccountOwner>),
+    Solvency(SolvencyReport),
+}
+
+/// Result of checking that balances are backed by native tokens and by recorded flows
+#[derive(Clone, Debug, Deserialize, Serialize, SimpleObject)]
+pub struct SolvencyReport {
+    pub accounts: Amount,
+    pub escrowed: Amount,
+    pub reserve: Amount,
+    // Native tokens in the application's account; must cover accounts, escrows and reserve
+    pub native: Amount,
+    pub outstanding_debts: Amount,
+    // Deposits, reserve funding, funds and pots from other chains and collected debts
+    pub inflows: Amount,
+    // Current holdings plus withdrawals, funds, pots and fees sent away and paid debts
+    pub outflows: Amount,
+    pub discrepancy: Amount,
+    // Owners whose balance isn't the one their ledger ends on
+    pub mismatched_owners: Vec<OwnerMismatch>,
+    // Ledger entries whose balance doesn't follow from the owner's previous one
+    pub broken_entries: Vec<LedgerEntry>,
+    pub solvent: bool,
+    pub issues: Vec<String>,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize, SimpleObject)]
+pub struct OwnerMismatch {
+    pub owner: AccountOwner,
+    pub balance: Amount,
+    pub ledger_balance: Amount,
 }
 
 #[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct BankrollParameters {
     pub master_chain: ChainId,
     pub bonus: Amount,
+    pub admin: AccountOwner,
+    // Debts not settled within this window expire
+    pub debt_timeout_seconds: u64,
+    // Charged as a debt of its own when a debt expires unpaid; the original can still be paid late
+    pub late_penalty_percent: u32,
+    // Applications built from these modules and created on master_chain may move funds (e.g. trivia).
+    // Parameters are the same on every chain, unlike state, and trivia's ID can't be known here
+    // because trivia's own parameters name this application.
+    pub trusted_modules: Vec<ModuleId>,
+}
+
+#[derive(Debug, Error)]
+pub enum BankrollError {
+    #[error("Operation is neither signed nor called by an application")]
+    Unauthenticated,
+
+    #[error("Application {0} is not allowed to move funds")]
+    UntrustedCaller(ApplicationId),
+
+    #[error("Only the bankroll admin can do this")]
+    NotAdmin,
+
+    #[error("{owner} has {balance} but {amount} is needed")]
+    InsufficientFunds {
+        owner: AccountOwner,
+        balance: Amount,
+        amount: Amount,
+    },
+
+    #[error("Reserve holds {reserve} but {amount} is needed")]
+    InsufficientReserve { reserve: Amount, amount: Amount },
+
+    #[error("Balance of {0} would overflow")]
+    Overflow(AccountOwner),
+
+    #[error("Daily bonus was already claimed today")]
+    BonusAlreadyClaimed,
+
+    #[error("Game {0} already has an escrow")]
+    EscrowExists(u64),
+
+    #[error("Game {0} has no escrow")]
+    UnknownEscrow(u64),
+
+    #[error("Escrow holds {balance} but {requested} was requested")]
+    EscrowMismatch { balance: Amount, requested: Amount },
+
+    #[error("Debt {0} does not exist")]
+    UnknownDebt(u64),
+
+    #[error("Debt {0} was settled by the wrong chain")]
+    WrongDebtChain(u64),
 }
\ No newline at end of file
@@ -7,11 +7,19 @@ use std::sync::Arc;
 use async_graphql::{EmptySubscription, Object, Schema};
 use linera_sdk::{
     graphql::GraphQLMutationRoot,
-    linera_base_types::WithServiceAbi,
+    linera_base_types::{AccountOwner, Amount, ChainId, ModuleId, WithServiceAbi},
     views::View,
     Service, ServiceRuntime,
 };
-use state::BankrollState;
+use abi::player_stats::PlayerStats;
+use crate::SolvencyReport;
+use state::{
+    BankrollState, ChainBalance, DailyBonus, DebtRecord, DebtStatus, EscrowKey, GameEscrow, LedgerEntry,
+    Treasury,
+};
+
+/// Most ledger entries returned (or scanned per step) at once
+const MAX_PAGE_SIZE: u64 = 200;
 
 pub struct BankrollService {
     state: Arc<BankrollState>,
@@ -42,6 +50,7 @@ impl Service for BankrollService {
         let schema = Schema::build(
             QueryRoot {
                 state: self.state.clone(),
+                runtime: self.runtime.clone(),
             },
             crate::BankrollOperation::mutation_root(self.runtime.clone()),
             EmptySubscription,
@@ -54,6 +63,7 @@ impl Service for BankrollService {
 
 struct QueryRoot {
     state: Arc<BankrollState>,
+    runtime: Arc<ServiceRuntime<BankrollService>>,
 }
 
 #[Object]
@@ -64,23 +74,145 @@ impl QueryRoot {
             .unwrap_or(Amount::ZERO)
     }
 
+    // Native tokens actually held by bankroll on this chain
+    async fn native_holdings(&self) -> Amount {
+        let vault = AccountOwner::from(self.runtime.application_id().forget_abi());
+        self.runtime.owner_balance(vault)
+    }
+
     async fn daily_bonus_amount(&self) -> Amount {
         self.runtime.application_parameters().bonus
     }
 
-    async fn total_pot(&self) -> Amount {
-        // Sum all pots or track separately
-        Amount::ZERO // Placeholder — improve if needed
+    async fn bonus_reserve(&self) -> Amount {
+        *self.state.bonus_reserve.get()
+    }
+
+    async fn daily_bonus(&self, owner: AccountOwner) -> DailyBonus {
+        self.state.daily_bonus.get(&owner).await
+            .expect("Failed to read")
+            .unwrap_or_default()
+    }
+
+    // Bonus-related stats; game stats are tracked by trivia
+    async fn player_stats(&self, owner: AccountOwner) -> PlayerStats {
+        let now = self.runtime.system_time();
+        let bonus = self.state.daily_bonus.get(&owner).await
+            .expect("Failed to read")
+            .unwrap_or_default();
+
+        PlayerStats {
+            owner,
+            current_streak: bonus.current_streak(now),
+            best_streak: bonus.best_streak,
+            avg_answer_time_ms: 0,
+            games_today: 0,
+            daily_bonus_claimed: bonus.claimed_today(now),
+        }
+    }
+
+    async fn escrow(&self, play_chain: ChainId, game_id: u64) -> Option<GameEscrow> {
+        let key = EscrowKey { play_chain, game_id };
+        self.state.escrows.get(&key).await.expect("Failed to read")
+    }
+
+    async fn escrows(&self) -> Vec<GameEscrow> {
+        let mut list = Vec::new();
+        self.state.escrows.for_each_index_value(|_, escrow| {
+            list.push(escrow.into_owned());
+            Ok(())
+        }).await.expect("Failed to read");
+        list
+    }
+
+    // Oldest first; pass the last id seen as `after` to get the next page
+    async fn transactions(&self, owner: AccountOwner, after: Option<u64>, limit: Option<u64>) -> Vec<LedgerEntry> {
+        let limit = limit.unwrap_or(50).min(MAX_PAGE_SIZE) as usize;
+        let ids = match self.state.ledger_by_owner.try_load_entry(&owner).await.expect("Failed to read") {
+            Some(ids) => ids,
+            None => return Vec::new(),
+        };
+
+        // Ids are in ascending order: binary search for the first one past `after`
+        let (mut start, mut end) = (0, ids.count());
+        if let Some(after) = after {
+            while start < end {
+                let mid = (start + end) / 2;
+                let id = ids.get(mid).await.expect("Failed to read").expect("Index is in range");
+                if id <= after {
+                    start = mid + 1;
+                } else {
+                    end = mid;
+                }
+            }
+        }
+
+        let page = ids.read(start..(start + limit).min(ids.count())).await.expect("Failed to read");
+        let indices = page.into_iter().map(|id| id as usize).collect();
+        self.state.ledger.multi_get(indices).await.expect("Failed to read").into_iter().flatten().collect()
+    }
+
+    async fn debt(&self, debt_id: u64) -> Option<DebtRecord> {
+        self.state.debt_log.get(&debt_id).await.expect("Failed to read")
     }
 
-    async fn public_chain_balances(&self) -> Vec<(ChainId, Amount)> {
+    // Both filters are optional
+    async fn debts(&self, status: Option<DebtStatus>, target_chain: Option<ChainId>) -> Vec<DebtRecord> {
         let mut list = Vec::new();
-        let keys = self.state.public_balances.indices().await.unwrap_or_default();
-        for key in keys {
-            if let Some(amount) = self.state.public_balances.get(&key).await.unwrap() {
-                list.push((key, amount));
+        self.state.debt_log.for_each_index_value(|_, debt| {
+            if status.is_none_or(|s| s == debt.status)
+                && target_chain.is_none_or(|c| c == debt.target_chain)
+            {
+                list.push(debt.into_owned());
+            }
+            Ok(())
+        }).await.expect("Failed to read");
+        list
+    }
+
+    async fn total_pot(&self) -> Amount {
+        self.state.treasury.get().escrowed
+    }
+
+    // Game fees sent on to master, kept apart from pots
+    async fn fees_forwarded(&self) -> Amount {
+        self.state.treasury.get().fees_forwarded
+    }
+
+    async fn treasury(&self) -> Treasury {
+        self.state.treasury.get().clone()
+    }
+
+    async fn solvency(&self) -> SolvencyReport {
+        let vault = AccountOwner::from(self.runtime.application_id().forget_abi());
+        self.state.solvency(self.runtime.owner_balance(vault)).await
+    }
+
+    async fn trusted_modules(&self) -> Vec<ModuleId> {
+        self.runtime.application_parameters().trusted_modules
+    }
+
+    // Every chain that reported holdings or was sent a pot
+    async fn public_chain_balances(&self) -> Vec<ChainBalance> {
+        let mut chains = self.state.public_balances.indices().await.expect("Failed to read");
+        for chain_id in self.state.pots_by_chain.indices().await.expect("Failed to read") {
+            if !chains.contains(&chain_id) {
+                chains.push(chain_id);
             }
         }
+
+        let mut list = Vec::new();
+        for chain_id in chains {
+            list.push(ChainBalance {
+                chain_id,
+                reported: self.state.public_balances.get(&chain_id).await
+                    .expect("Failed to read")
+                    .unwrap_or_default(),
+                pots_sent: self.state.pots_by_chain.get(&chain_id).await
+                    .expect("Failed to read")
+                    .unwrap_or_default(),
+            });
+        }
         list
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,219 @@
+//! Runs a game through the bankroll's bookkeeping and checks the books still balance afterwards
+
+use abi::trivia::Payout;
+use bankroll::state::{BankrollState, EscrowKey, LedgerKind, Link};
+use futures::executor::block_on;
+use linera_sdk::linera_base_types::{AccountOwner, Amount, ChainId, CryptoHash, Timestamp};
+use linera_sdk::views::{KeyValueStore, View, ViewStorageContext};
+
+const DEPOSIT: Amount = Amount::from_tokens(100);
+const STAKE: Amount = Amount::from_tokens(10);
+const FEE: Amount = Amount::from_tokens(2);
+const RESERVE: Amount = Amount::from_tokens(50);
+const BONUS: Amount = Amount::from_tokens(5);
+
+fn owner(n: u8) -> AccountOwner {
+    AccountOwner::Address20([n; 20])
+}
+
+fn chain(n: u8) -> ChainId {
+    ChainId(CryptoHash::from([n; 32]))
+}
+
+fn now() -> Timestamp {
+    Timestamp::from(1_000)
+}
+
+async fn empty_state() -> BankrollState {
+    let context = ViewStorageContext::new_unchecked(KeyValueStore::mock().to_mut(), Vec::new(), ());
+    BankrollState::load(context).await.expect("Failed to load state")
+}
+
+/// One chain's bankroll and the native tokens its application account holds
+struct Bankroll {
+    chain_id: ChainId,
+    state: BankrollState,
+    native: Amount,
+}
+
+impl Bankroll {
+    async fn new(chain_id: ChainId) -> Self {
+        Bankroll { chain_id, state: empty_state().await, native: Amount::ZERO }
+    }
+
+    // Deposit: the signer's tokens come in with the credit
+    async fn deposit(&mut self, owner: AccountOwner, amount: Amount) {
+        self.state.credit(owner, amount, LedgerKind::Deposit, Link::default(), now()).await.unwrap();
+        self.native.saturating_add_assign(amount);
+    }
+
+    // FundReserve: the admin's tokens go into the reserve
+    fn fund_reserve(&mut self, amount: Amount) {
+        self.state.fund_reserve(amount);
+        self.native.saturating_add_assign(amount);
+    }
+
+    // SendFunds here, then the Funds message with its tokens on `target`
+    async fn send_funds(&mut self, target: &mut Bankroll, owner: AccountOwner, amount: Amount) {
+        self.state.debit(owner, amount, LedgerKind::ChainTransfer, Link::default(), now()).await.unwrap();
+        self.native = self.native.saturating_sub(amount);
+
+        target.state.credit(owner, amount, LedgerKind::ChainTransfer, Link::default(), now()).await.unwrap();
+        target.native.saturating_add_assign(amount);
+    }
+
+    // ReleaseEscrow here, then the TokenPot message with the fee on master
+    async fn release_escrow(&mut self, master: &mut Bankroll, key: &EscrowKey, payouts: Vec<Payout>, fee: Amount) {
+        self.state.release_escrow(key, payouts, fee, master.chain_id, now()).await.unwrap();
+        self.native = self.native.saturating_sub(fee);
+
+        master.state.receive_pot(fee);
+        master.native.saturating_add_assign(fee);
+    }
+
+    // PayBonus on master, then the Bonus message with its tokens on `target`
+    async fn pay_bonus(&mut self, target: &mut Bankroll, owner: AccountOwner, amount: Amount) -> Amount {
+        let paid = self.state.pay_bonus(owner, amount, false, now()).await.unwrap();
+        self.native = self.native.saturating_sub(paid);
+
+        target.state.receive_bonus(owner, paid, now()).await.unwrap();
+        target.native.saturating_add_assign(paid);
+        paid
+    }
+
+    async fn assert_solvent(&self) {
+        let report = self.state.solvency(self.native).await;
+        assert!(report.solvent, "{}: {:?}", self.chain_id, report.issues);
+        assert_eq!(report.discrepancy, Amount::ZERO);
+        assert!(report.mismatched_owners.is_empty());
+        assert!(report.broken_entries.is_empty());
+    }
+}
+
+/// Two players deposit, play one game on `play_chain` and the fee goes to master as a pot.
+/// Returns the native tokens the bankroll holds afterwards.
+async fn play_game(state: &mut BankrollState, play_chain: ChainId, master_chain: ChainId) -> Amount {
+    let (winner, loser) = (owner(1), owner(2));
+
+    for player in [winner, loser] {
+        state.credit(player, DEPOSIT, LedgerKind::Deposit, Link::default(), now()).await.unwrap();
+    }
+    let native = DEPOSIT.saturating_mul(2);
+
+    let key = EscrowKey { play_chain, game_id: 7 };
+    state.lock_stakes(key.clone(), vec![winner, loser], STAKE, now()).await.unwrap();
+
+    let pot = STAKE.saturating_mul(2);
+    let payouts = vec![Payout { player: winner, amount: pot.saturating_sub(FEE) }];
+    state.release_escrow(&key, payouts, FEE, master_chain, now()).await.unwrap();
+
+    // The fee leaves with the tokens backing it
+    native.saturating_sub(FEE)
+}
+
+#[test]
+fn books_balance_after_a_game() {
+    block_on(async {
+        let mut state = empty_state().await;
+        let native = play_game(&mut state, chain(1), chain(0)).await;
+
+        let report = state.solvency(native).await;
+        assert!(report.solvent, "{:?}", report.issues);
+        assert_eq!(report.accounts, DEPOSIT.saturating_mul(2).saturating_sub(FEE));
+        assert_eq!(report.escrowed, Amount::ZERO);
+        assert_eq!(report.discrepancy, Amount::ZERO);
+        assert_eq!(state.treasury.get().fees_forwarded, FEE);
+        assert_eq!(state.treasury.get().pots_sent, Amount::ZERO);
+    });
+}
+
+#[test]
+fn books_balance_on_every_chain_a_game_touches() {
+    block_on(async {
+        let mut master = Bankroll::new(chain(0)).await;
+        let mut home = Bankroll::new(chain(1)).await;
+        let mut play = Bankroll::new(chain(2)).await;
+        let (winner, loser) = (owner(1), owner(2));
+
+        master.fund_reserve(RESERVE);
+        for player in [winner, loser] {
+            home.deposit(player, DEPOSIT).await;
+            home.send_funds(&mut play, player, STAKE).await;
+        }
+
+        // A game that was aborted, then one that was played
+        let aborted = EscrowKey { play_chain: play.chain_id, game_id: 1 };
+        play.state.lock_stakes(aborted.clone(), vec![winner, loser], STAKE, now()).await.unwrap();
+        play.state.refund_escrow(&aborted, now()).await.unwrap();
+
+        let played = EscrowKey { play_chain: play.chain_id, game_id: 2 };
+        play.state.lock_stakes(played.clone(), vec![winner, loser], STAKE, now()).await.unwrap();
+        play.assert_solvent().await;
+
+        let payouts = vec![Payout { player: winner, amount: STAKE.saturating_mul(2).saturating_sub(FEE) }];
+        play.release_escrow(&mut master, &played, payouts, FEE).await;
+
+        let winnings = play.state.balance_of(winner).await;
+        play.send_funds(&mut home, winner, winnings).await;
+        let paid = master.pay_bonus(&mut home, loser, BONUS).await;
+        assert_eq!(paid, BONUS);
+
+        for bankroll in [&master, &home, &play] {
+            bankroll.assert_solvent().await;
+        }
+        assert_eq!(*master.state.bonus_reserve.get(), RESERVE.saturating_add(FEE).saturating_sub(BONUS));
+        assert_eq!(home.state.balance_of(loser).await, DEPOSIT.saturating_sub(STAKE).saturating_add(BONUS));
+        assert_eq!(play.native, Amount::ZERO);
+    });
+}
+
+#[test]
+fn missing_tokens_are_reported() {
+    block_on(async {
+        let mut state = empty_state().await;
+        let native = play_game(&mut state, chain(1), chain(0)).await;
+
+        let report = state.solvency(native.saturating_sub(Amount::ONE)).await;
+        assert!(!report.solvent);
+        assert_eq!(report.issues.len(), 1, "{:?}", report.issues);
+    });
+}
+
+#[test]
+fn balances_changed_outside_the_ledger_are_reported() {
+    block_on(async {
+        let mut state = empty_state().await;
+        let native = play_game(&mut state, chain(1), chain(0)).await;
+
+        // Tokens arrive with the balance, so only the recorded flows and the ledger can tell
+        let balance = state.balance_of(owner(2)).await;
+        state.accounts.insert(&owner(2), balance.saturating_add(STAKE)).unwrap();
+        let report = state.solvency(native.saturating_add(STAKE)).await;
+
+        assert!(!report.solvent);
+        assert_eq!(report.discrepancy, STAKE);
+        assert_eq!(report.mismatched_owners.len(), 1);
+        let mismatch = &report.mismatched_owners[0];
+        assert_eq!(mismatch.owner, owner(2));
+        assert_eq!(mismatch.balance, balance.saturating_add(STAKE));
+        assert_eq!(mismatch.ledger_balance, balance);
+    });
+}
+
+#[test]
+fn entries_that_skip_a_balance_change_are_reported() {
+    block_on(async {
+        let mut state = empty_state().await;
+        let native = play_game(&mut state, chain(1), chain(0)).await;
+
+        // Logged as a change of STAKE, but the balance it records moved by twice that
+        let balance = state.balance_of(owner(1)).await.saturating_add(STAKE.saturating_mul(2));
+        state.record(LedgerKind::Adjustment, Some(owner(1)), STAKE, Some(balance), Link::default(), now()).await;
+        let report = state.solvency(native).await;
+
+        assert!(!report.solvent);
+        assert_eq!(report.broken_entries.len(), 1);
+        assert_eq!(report.broken_entries[0].owner, Some(owner(1)));
+        assert_eq!(report.mismatched_owners[0].ledger_balance, balance);
+    });
+}
@@ -1,17 +1,44 @@
 #![cfg_attr(target_arch = "wasm32", no_main)]
 
+mod selection;
 mod state;
 
 use async_trait::async_trait;
 use linera_sdk::{
     Contract, ContractRuntime,
-    linera_base_types::{AccountOwner, Amount},
+    linera_base_types::{AccountOwner, Amount, ApplicationId, ChainId},
     abi::{WithContractAbi},
-    views::View,
+    views::{ReadGuardedView, RootView, View, ViewStorageContext},
 };
-use state::MasterState;
-use trivia::TriviaMessage;
-use abi::trivia::Question;
+use selection::{QuestionRng, SelectionSeed};
+use state::{MasterState, QuestionSet, Submission, SubmissionStatus};
+use crate::{MasterError, MasterOperation, MasterResponse, QuestionResult};
+use trivia::{TriviaAbi, TriviaMessage, TriviaOperation};
+use abi::trivia::{Question, QuestionError, QuestionFilter, QuestionInput};
+use std::collections::HashSet;
+
+/// How many past questions per play chain are kept out of new games
+const RECENT_QUESTIONS_PER_CHAIN: usize = 200;
+
+/// Most submissions one player can have waiting for review
+const MAX_PENDING_PER_SUBMITTER: u32 = 10;
+
+/// Random positions tried before a draw walks the set in order
+const RANDOM_PROBES: u64 = 16;
+
+/// Which questions a draw may return
+struct Slot<'a> {
+    level: Option<u8>,
+    outside: bool,
+    filter: &'a QuestionFilter,
+}
+
+impl Slot<'_> {
+    fn fits(&self, question: &Question) -> bool {
+        self.level.is_none_or(|level| question.difficulty == level)
+            && (self.filter.categories.is_empty() || self.filter.categories.contains(&question.category) != self.outside)
+    }
+}
 
 pub struct MasterContract {
     runtime: ContractRuntime<Self>,
@@ -27,7 +54,7 @@ impl WithContractAbi for MasterContract {
 #[async_trait]
 impl Contract for MasterContract {
     type Message = TriviaMessage;
-    type Parameters = ();
+    type Parameters = crate::MasterParameters;
     type InstantiationArgument = (Vec<Question>, AccountOwner);
     type EventValue = ();
 
@@ -44,31 +71,227 @@ impl Contract for MasterContract {
 
     async fn instantiate(&mut self, argument: Self::InstantiationArgument) {
         let (questions, admin_owner) = argument;
-        self.state.question_bank.set(questions);
+        let next_id = questions.iter().map(|q| q.id + 1).max().unwrap_or(1);
+        self.state.next_question_id.set(next_id);
+        for question in questions {
+            if let Err(error) = question.validate() {
+                panic!("{}", MasterError::InvalidQuestion(error));
+            }
+            self.add_question(question).await;
+        }
         self.state.admin.set(Some(admin_owner));
         self.state.treasury.set(Amount::ZERO);
     }
 
-    async fn execute_operation(&mut self, _op: Self::Operation) -> Self::Response {}
+    async fn execute_operation(&mut self, operation: MasterOperation) -> MasterResponse {
+        // Trivia on a play chain, during a game that may have no signer at hand
+        let operation = match operation {
+            MasterOperation::RequestQuestions { game_id, count, filter } => {
+                let trivia_app = self.trivia_caller().unwrap_or_else(|error| panic!("{error}"));
+                self.send_to_master(TriviaMessage::RequestQuestions { game_id, count, filter, trivia_app });
+                return MasterResponse::Ok;
+            }
+            MasterOperation::SendProtocolFee { amount } => {
+                if let Err(error) = self.trivia_caller() {
+                    panic!("{error}");
+                }
+                self.send_to_master(TriviaMessage::SendProtocolFee { amount });
+                return MasterResponse::Ok;
+            }
+            operation => operation,
+        };
+
+        let access = match operation {
+            MasterOperation::ApproveSubmission { .. }
+            | MasterOperation::RejectSubmission { .. }
+            | MasterOperation::EditSubmission { .. } => self.check_moderator().await,
+            MasterOperation::SubmitQuestion { .. } => self.check_submitter(),
+            _ => self.check_admin(),
+        };
+        let signer = match access {
+            Ok(signer) => signer,
+            Err(error) => panic!("{error}"),
+        };
+
+        match operation {
+            // Each question is checked on its own; bad ones don't stop the rest
+            MasterOperation::AddQuestions { questions } => {
+                let mut results = Vec::new();
+                for input in questions {
+                    if let Err(error) = input.validate() {
+                        results.push(Err(error));
+                        continue;
+                    }
+                    let id = self.state.allocate_question_id();
+                    self.add_question(input.into_question(id)).await;
+                    results.push(Ok(id));
+                }
+                MasterResponse::Questions(results)
+            }
+
+            MasterOperation::UpdateQuestion { id, question } => {
+                let result = self.update_question(id, question).await;
+                MasterResponse::Questions(vec![result])
+            }
+
+            MasterOperation::RetireQuestion { id } => {
+                let result = self.retire_question(id).await;
+                MasterResponse::Questions(vec![result])
+            }
+
+            MasterOperation::MigrateQuestionBank { batch } => {
+                let left = self.state.migrate_question_bank(batch as usize).await;
+                MasterResponse::Migrated { left: left as u64 }
+            }
+
+            MasterOperation::AddModerator { owner } => {
+                self.state.moderators.insert(&owner).expect("Failed to add moderator");
+                MasterResponse::Ok
+            }
+
+            MasterOperation::RemoveModerator { owner } => {
+                self.state.moderators.remove(&owner).expect("Failed to remove moderator");
+                MasterResponse::Ok
+            }
+
+            MasterOperation::ApproveSubmission { id } => {
+                let mut submission = self.pending_submission(id).await;
+                if let Err(error) = submission.question.validate() {
+                    panic!("{}", MasterError::InvalidQuestion(error));
+                }
+
+                let question_id = self.state.allocate_question_id();
+                self.add_question(submission.question.clone().into_question(question_id)).await;
+
+                // Credit the author in their profile, through master on their own chain
+                self.runtime
+                    .prepare_message(TriviaMessage::QuestionApproved {
+                        submission_id: id,
+                        question_id,
+                        trivia_app: submission.submitter_app,
+                    })
+                    .send_to(submission.submitter_chain);
+
+                submission.status = SubmissionStatus::Approved;
+                submission.question_id = Some(question_id);
+                self.close_submission(submission, signer).await;
+                MasterResponse::Questions(vec![Ok(question_id)])
+            }
+
+            MasterOperation::RejectSubmission { id, reason } => {
+                let mut submission = self.pending_submission(id).await;
+                submission.status = SubmissionStatus::Rejected;
+                submission.reason = Some(reason);
+                self.close_submission(submission, signer).await;
+                MasterResponse::Ok
+            }
+
+            // Moderators fix small mistakes instead of rejecting
+            MasterOperation::EditSubmission { id, question } => {
+                if let Err(error) = question.validate() {
+                    panic!("{}", MasterError::InvalidQuestion(error));
+                }
+
+                let mut submission = self.pending_submission(id).await;
+                submission.question = question;
+                self.state.submissions.insert(&id, submission).expect("Failed to store submission");
+                MasterResponse::Ok
+            }
+
+            // On the player's chain: the queue lives on master's own chain
+            MasterOperation::SubmitQuestion { question } => {
+                if let Err(error) = question.validate() {
+                    panic!("{}", MasterError::InvalidQuestion(error));
+                }
+                let trivia_app = self.trivia_caller().expect("Checked by check_submitter");
+                let master = self.runtime.application_creator_chain_id();
+                self.runtime
+                    .prepare_message(TriviaMessage::SubmitQuestion { question, trivia_app })
+                    .with_authentication()
+                    .send_to(master);
+                MasterResponse::Ok
+            }
+
+            MasterOperation::RequestQuestions { .. } | MasterOperation::SendProtocolFee { .. } => {
+                unreachable!("Forwarded above")
+            }
+        }
+    }
 
     async fn execute_message(&mut self, message: Self::Message) {
         match message {
-            TriviaMessage::RequestQuestions { count } => {
-                let bank = self.state.question_bank.get();
-                let mut selected = Vec::new();
-                let mut ids = Vec::new();
-                for i in 0..count.min(bank.len() as u8) {
-                    let q = &bank[i as usize];
-                    ids.push(q.id);
-                    selected.push(q.clone());
-                }
-                let origin = self.runtime.message_sender().expect("No sender");
+            // From master on a play chain, on behalf of trivia there
+            TriviaMessage::RequestQuestions { game_id, count, filter, trivia_app } => {
+                let origin = self.runtime.message_origin_chain_id().expect("No origin chain");
+                let (questions, matched) = self.select_questions(origin, game_id, count as usize, &filter).await;
+                let question_ids = questions.iter().map(|q| q.id).collect();
+
                 self.runtime.prepare_message(TriviaMessage::ReceiveQuestions {
-                    question_ids: ids,
-                    questions: selected,
+                    game_id,
+                    question_ids,
+                    questions,
+                    matched,
+                    trivia_app,
                 })
                 .send_to(origin);
             }
+            // Play chain: hand the questions to the trivia app that asked for them
+            TriviaMessage::ReceiveQuestions { game_id, question_ids, questions, matched, trivia_app } => {
+                if self.runtime.message_origin_chain_id() != Some(self.runtime.application_creator_chain_id()) {
+                    return;
+                }
+                self.runtime.call_application(
+                    false,
+                    trivia_app.with_abi::<TriviaAbi>(),
+                    &TriviaOperation::ReceiveQuestions { game_id, question_ids, questions, matched },
+                );
+            }
+            // Any player, via trivia and master on their own chain
+            TriviaMessage::SubmitQuestion { question, trivia_app } => {
+                let Some(submitter) = self.runtime.authenticated_signer() else {
+                    return;
+                };
+                if question.validate().is_err() {
+                    return;
+                }
+
+                // Dropped rather than queued once the player has too many waiting
+                let pending = self.state.pending_by_submitter.get_mut_or_default(&submitter).await
+                    .expect("Failed to read pending submissions");
+                if *pending >= MAX_PENDING_PER_SUBMITTER {
+                    return;
+                }
+                *pending += 1;
+
+                let id = *self.state.next_submission_id.get();
+                self.state.next_submission_id.set(id + 1);
+
+                let submission = Submission {
+                    id,
+                    submitter,
+                    submitter_chain: self.runtime.message_origin_chain_id().expect("No origin chain"),
+                    submitter_app: trivia_app,
+                    question,
+                    status: SubmissionStatus::Pending,
+                    submitted_at: self.runtime.system_time(),
+                    reviewed_by: None,
+                    reason: None,
+                    question_id: None,
+                };
+                self.state.submissions.insert(&id, submission).expect("Failed to store submission");
+                self.state.pending_submissions.insert(&id).expect("Failed to queue submission");
+            }
+            // Player's chain: pass the news on to the trivia app that submitted the question
+            TriviaMessage::QuestionApproved { submission_id, question_id, trivia_app } => {
+                if self.runtime.message_origin_chain_id() != Some(self.runtime.application_creator_chain_id()) {
+                    return;
+                }
+                self.runtime.call_application(
+                    false,
+                    trivia_app.with_abi::<TriviaAbi>(),
+                    &TriviaOperation::QuestionApproved { submission_id, question_id },
+                );
+            }
             TriviaMessage::SendProtocolFee { amount } => {
                 let mut treasury = self.state.treasury.get_mut();
                 treasury.saturating_add_assign(amount);
@@ -76,4 +299,256 @@ impl Contract for MasterContract {
             _ => {}
         }
     }
-}
\ No newline at end of file
+}
+
+impl MasterContract {
+    fn check_admin(&mut self) -> Result<AccountOwner, MasterError> {
+        let signer = self.runtime.authenticated_signer().ok_or(MasterError::Unauthenticated)?;
+        if Some(signer) == *self.state.admin.get() {
+            Ok(signer)
+        } else {
+            Err(MasterError::NotAdmin)
+        }
+    }
+
+    // Players submit through trivia, which calls here with their signature
+    fn check_submitter(&mut self) -> Result<AccountOwner, MasterError> {
+        let signer = self.runtime.authenticated_signer().ok_or(MasterError::Unauthenticated)?;
+        self.trivia_caller()?;
+        Ok(signer)
+    }
+
+    fn send_to_master(&mut self, message: TriviaMessage) {
+        let master = self.runtime.application_creator_chain_id();
+        self.runtime.prepare_message(message).send_to(master);
+    }
+
+    // The trivia application calling master on this chain, which answers go back to
+    fn trivia_caller(&mut self) -> Result<ApplicationId, MasterError> {
+        let caller = self.runtime.authenticated_caller_id().ok_or(MasterError::NotCalledByApplication)?;
+        let description = self.runtime.read_application_description(caller);
+        let trusted = description.module_id == self.runtime.application_parameters().trivia_module
+            && description.creator_chain_id == self.runtime.application_creator_chain_id();
+        if trusted {
+            Ok(caller)
+        } else {
+            Err(MasterError::UntrustedCaller(caller))
+        }
+    }
+
+    // The admin counts as a moderator
+    async fn check_moderator(&mut self) -> Result<AccountOwner, MasterError> {
+        let signer = self.runtime.authenticated_signer().ok_or(MasterError::Unauthenticated)?;
+        let moderator = self.state.moderators.contains(&signer).await
+            .expect("Failed to read moderators");
+        if moderator || Some(signer) == *self.state.admin.get() {
+            Ok(signer)
+        } else {
+            Err(MasterError::NotModerator)
+        }
+    }
+
+    async fn pending_submission(&mut self, id: u64) -> Submission {
+        let submission = self.state.submissions.get(&id).await
+            .expect("Failed to read submission")
+            .unwrap_or_else(|| panic!("{}", MasterError::UnknownSubmission(id)));
+        if submission.status != SubmissionStatus::Pending {
+            panic!("{}", MasterError::AlreadyReviewed(id));
+        }
+        submission
+    }
+
+    async fn close_submission(&mut self, mut submission: Submission, reviewer: AccountOwner) {
+        let id = submission.id;
+        let pending = self.state.pending_by_submitter.get_mut_or_default(&submission.submitter).await
+            .expect("Failed to read pending submissions");
+        *pending = pending.saturating_sub(1);
+        submission.reviewed_by = Some(reviewer);
+        self.state.submissions.insert(&id, submission).expect("Failed to store submission");
+        self.state.pending_submissions.remove(&id).expect("Failed to dequeue submission");
+    }
+
+    async fn add_question(&mut self, question: Question) {
+        self.state.index_question(&question).await;
+        self.state.insert_question(question);
+    }
+
+    // Random active questions the chain hasn't seen lately, closest to the
+    // filter first. Falls back to recent questions, then to other categories,
+    // so a game always gets `count` questions if the bank has them; also
+    // returns how many fit the filter exactly
+    async fn select_questions(
+        &mut self,
+        chain_id: ChainId,
+        game_id: u64,
+        count: usize,
+        filter: &QuestionFilter,
+    ) -> (Vec<Question>, u32) {
+        let mut rng = QuestionRng::new(&SelectionSeed {
+            chain_id,
+            game_id,
+            block_height: self.runtime.block_height(),
+            timestamp: self.runtime.system_time(),
+        });
+
+        let mut recent = self.state.recent_questions.get(&chain_id).await
+            .expect("Failed to read recent questions")
+            .unwrap_or_default();
+        let mut taken: HashSet<u64> = recent.iter().copied().collect();
+
+        // Sets a fresh draw came back empty from, and the chain's recent questions, oldest
+        // first, read the first time a slot falls back to them
+        let mut exhausted = HashSet::new();
+        let mut seen: Option<Vec<Question>> = None;
+
+        let mut selected = Vec::new();
+        let mut matched = 0;
+        for index in 0..count {
+            let target = filter.difficulty.target(index, count);
+            // Closest difficulty first
+            let levels: Vec<Option<u8>> = match target {
+                None => vec![None],
+                Some(target) => {
+                    let mut levels: Vec<u8> = (1..=5).collect();
+                    levels.sort_by_key(|level| (level.abs_diff(target), *level));
+                    levels.into_iter().map(Some).collect()
+                }
+            };
+
+            // Fresh and recent questions in the wanted categories, then the same outside them
+            let mut choice = None;
+            'tiers: for (outside, repeat) in [(false, false), (false, true), (true, false), (true, true)] {
+                if outside && filter.categories.is_empty() {
+                    break;
+                }
+                for level in &levels {
+                    let slot = Slot { level: *level, outside, filter };
+                    let question = if repeat {
+                        if seen.is_none() {
+                            seen = Some(self.recent_active(&recent).await);
+                        }
+                        let seen = seen.as_mut().expect("Loaded above");
+                        seen.iter().position(|q| slot.fits(q)).map(|position| seen.remove(position))
+                    } else if exhausted.contains(&(outside, *level)) {
+                        None
+                    } else {
+                        let question = self.draw_fresh(&slot, &taken, &mut rng).await;
+                        if question.is_none() {
+                            exhausted.insert((outside, *level));
+                        }
+                        question
+                    };
+
+                    // Malformed questions from before validation are left out
+                    if let Some(mut question) = question {
+                        taken.insert(question.id);
+                        if rng.shuffle_choices(&mut question) {
+                            choice = Some((question, outside, *level == target));
+                            break 'tiers;
+                        }
+                    }
+                }
+            }
+
+            let Some((question, outside, exact)) = choice else {
+                break;
+            };
+            if !outside && exact {
+                matched += 1;
+            }
+            selected.push(question);
+        }
+
+        let sent: HashSet<u64> = selected.iter().map(|q| q.id).collect();
+        recent.retain(|id| !sent.contains(id));
+        recent.extend(selected.iter().map(|q| q.id));
+        let overflow = recent.len().saturating_sub(RECENT_QUESTIONS_PER_CHAIN);
+        recent.drain(..overflow);
+        self.state.recent_questions.insert(&chain_id, recent).expect("Failed to store recent questions");
+
+        (selected, matched)
+    }
+
+    // A random active question for the slot that isn't `taken`. Reads one ID per probe rather
+    // than whole sets: random positions first, then every position from a random start
+    async fn draw_fresh(&self, slot: &Slot<'_>, taken: &HashSet<u64>, rng: &mut QuestionRng) -> Option<Question> {
+        // Inside the categories, draw from theirs; otherwise from the level's set, or every question
+        let mut guards: Vec<ReadGuardedView<QuestionSet<ViewStorageContext>>> = Vec::new();
+        let in_categories = !slot.outside && !slot.filter.categories.is_empty();
+        if in_categories {
+            for category in &slot.filter.categories {
+                guards.extend(self.state.by_category.try_load_entry(category).await.expect("Failed to load category"));
+            }
+        } else if let Some(level) = slot.level {
+            guards.extend(self.state.by_difficulty.try_load_entry(&level).await.expect("Failed to load difficulty"));
+        }
+        let sets: Vec<&QuestionSet<ViewStorageContext>> = if in_categories || slot.level.is_some() {
+            guards.iter().map(|guard| &**guard).collect()
+        } else {
+            vec![&self.state.active_questions]
+        };
+
+        let total: u64 = sets.iter().map(|set| set.len()).sum();
+        if total == 0 {
+            return None;
+        }
+        let start = rng.below(total as usize) as u64;
+        for probe in 0..RANDOM_PROBES + total {
+            let mut position = match probe.checked_sub(RANDOM_PROBES) {
+                None => rng.below(total as usize) as u64,
+                Some(step) => (start + step) % total,
+            };
+            let set = sets.iter()
+                .find(|set| {
+                    let inside = position < set.len();
+                    if !inside {
+                        position -= set.len();
+                    }
+                    inside
+                })
+                .expect("Position is below the total");
+
+            let id = set.at(position).await.expect("Positions are dense");
+            if taken.contains(&id) {
+                continue;
+            }
+            if let Some(question) = self.state.question(id).await {
+                if slot.fits(&question) {
+                    return Some(question);
+                }
+            }
+        }
+        None
+    }
+
+    // Questions the chain was sent lately that are still active, oldest first
+    async fn recent_active(&self, recent: &[u64]) -> Vec<Question> {
+        let mut questions = Vec::new();
+        for id in recent {
+            if self.state.is_active(*id).await {
+                questions.extend(self.state.question(*id).await);
+            }
+        }
+        questions
+    }
+
+    // Edits keep the question's ID so past games still point at it
+    async fn update_question(&mut self, id: u64, input: QuestionInput) -> QuestionResult {
+        input.validate()?;
+        let old = self.state.question(id).await.ok_or(QuestionError::UnknownQuestion(id))?;
+        if !self.state.is_active(id).await {
+            return Err(QuestionError::Retired(id));
+        }
+
+        self.state.unindex_question(&old).await;
+        self.add_question(input.into_question(id)).await;
+        Ok(id)
+    }
+
+    async fn retire_question(&mut self, id: u64) -> QuestionResult {
+        let question = self.state.question(id).await.ok_or(QuestionError::UnknownQuestion(id))?;
+        self.state.unindex_question(&question).await;
+        self.state.retired_questions.insert(&id).expect("Failed to retire question");
+        Ok(id)
+    }
+}
@@ -1,18 +1,44 @@
+pub mod selection;
 pub mod state;
 
-use linera_sdk::abi::{ContractAbi, ServiceAbi};
+use linera_sdk::linera_base_types::{ApplicationId, ModuleId};
 use serde::{Deserialize, Serialize};
-use async_graphql::{Request, Response};
+use thiserror::Error;
 
-#[derive(Debug, Deserialize, Serialize)]
-pub struct MasterAbi;
+use abi::trivia::QuestionError;
 
-impl ContractAbi for MasterAbi {
-    type Operation = ();
-    type Response = ();
+pub use abi::master::{MasterAbi, MasterOperation, MasterResponse, QuestionResult};
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct MasterParameters {
+    // Only trivia applications from this module, created on master's chain, may call master.
+    // Trivia's own parameters name master, so its application ID can't be known here
+    pub trivia_module: ModuleId,
 }
 
-impl ServiceAbi for MasterAbi {
-    type Query = Request;
-    type QueryResponse = Response;
-}
\ No newline at end of file
+#[derive(Debug, Error)]
+pub enum MasterError {
+    #[error("Operation is not signed")]
+    Unauthenticated,
+
+    #[error("Only the master admin can do this")]
+    NotAdmin,
+
+    #[error("Only moderators can review submissions")]
+    NotModerator,
+
+    #[error("Submission {0} does not exist")]
+    UnknownSubmission(u64),
+
+    #[error("Submission {0} was already reviewed")]
+    AlreadyReviewed(u64),
+
+    #[error("Invalid question: {0}")]
+    InvalidQuestion(QuestionError),
+
+    #[error("Only trivia can call this")]
+    NotCalledByApplication,
+
+    #[error("Application {0} is not trivia")]
+    UntrustedCaller(ApplicationId),
+}
@@ -0,0 +1,61 @@
+use abi::trivia::Question;
+use linera_sdk::linera_base_types::{BcsHashable, BlockHeight, ChainId, CryptoHash, Timestamp};
+use serde::{Deserialize, Serialize};
+
+/// Everything a question draw is seeded from
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SelectionSeed {
+    pub chain_id: ChainId,
+    pub game_id: u64,
+    pub block_height: BlockHeight,
+    pub timestamp: Timestamp,
+}
+
+impl BcsHashable<'_> for SelectionSeed {}
+
+/// Deterministic shuffler (SplitMix64), so every validator draws the same
+/// questions, while the seed can't be known before the request's block
+pub struct QuestionRng(u64);
+
+impl QuestionRng {
+    pub fn new(seed: &SelectionSeed) -> Self {
+        let words: [u64; 4] = CryptoHash::new(seed).into();
+        QuestionRng(words[0] ^ words[1] ^ words[2] ^ words[3])
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    /// Uniform in `0..n`
+    pub fn below(&mut self, n: usize) -> usize {
+        ((self.next_u64() as u128 * n as u128) >> 64) as usize
+    }
+
+    /// Moves `count` randomly chosen items to the front, in random order
+    pub fn pick<T>(&mut self, items: &mut [T], count: usize) {
+        let count = count.min(items.len());
+        for i in 0..count {
+            let j = i + self.below(items.len() - i);
+            items.swap(i, j);
+        }
+    }
+
+    /// Shuffles the choices and keeps `correct_idx` pointing at the right one.
+    /// Returns false, leaving the question as it was, if `correct_idx` is not one of the choices
+    pub fn shuffle_choices(&mut self, question: &mut Question) -> bool {
+        let Some(correct) = question.choices.get(question.correct_idx as usize).cloned() else {
+            return false;
+        };
+        let len = question.choices.len();
+        self.pick(&mut question.choices, len);
+        question.correct_idx = question.choices.iter()
+            .position(|c| *c == correct)
+            .expect("Correct choice is still there") as u8;
+        true
+    }
+}
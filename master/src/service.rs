@@ -4,11 +4,22 @@ mod state;
 
 use std::sync::Arc;
 use async_graphql::{EmptySubscription, Object, Schema};
-use linera_sdk::{Service, ServiceRuntime, views::View};
-use state::MasterState;
+use linera_sdk::{
+    graphql::GraphQLMutationRoot,
+    linera_base_types::{AccountOwner, Amount},
+    Service, ServiceRuntime,
+    views::View,
+};
+use abi::trivia::Question;
+use state::{MasterState, Submission};
+use crate::MasterOperation;
+
+/// Most questions returned by one query
+const MAX_PAGE_SIZE: u64 = 200;
 
 pub struct MasterService {
     state: Arc<MasterState>,
+    runtime: Arc<ServiceRuntime<Self>>,
 }
 
 linera_sdk::service!(MasterService);
@@ -19,19 +30,22 @@ impl linera_sdk::abi::WithServiceAbi for MasterService {
 
 #[async_trait::async_trait]
 impl Service for MasterService {
-    type Parameters = ();
+    type Parameters = crate::MasterParameters;
 
     async fn new(runtime: ServiceRuntime<Self>) -> Self {
         let state = MasterState::load(runtime.root_view_storage_context())
             .await
             .expect("Failed to load state");
-        Self { state: Arc::new(state) }
+        Self {
+            state: Arc::new(state),
+            runtime: Arc::new(runtime),
+        }
     }
 
     async fn handle_query(&self, query: Self::Query) -> Self::QueryResponse {
         let schema = Schema::build(
             QueryRoot { state: self.state.clone() },
-            async_graphql::EmptyMutation,
+            MasterOperation::mutation_root(self.runtime.clone()),
             EmptySubscription,
         ).finish();
         schema.execute(query).await
@@ -47,4 +61,75 @@ impl QueryRoot {
     async fn treasury_balance(&self) -> Amount {
         *self.state.treasury.get()
     }
-}
\ No newline at end of file
+
+    async fn question(&self, id: u64) -> Option<Question> {
+        self.state.question(id).await
+    }
+
+    // Active questions, optionally filtered by category or difficulty
+    async fn questions(
+        &self,
+        category: Option<String>,
+        difficulty: Option<u8>,
+        offset: Option<u64>,
+        limit: Option<u64>,
+    ) -> Vec<Question> {
+        let mut ids = match (&category, difficulty) {
+            (Some(category), None) => self.state.category_ids(category).await,
+            (None, Some(difficulty)) => self.state.difficulty_ids(difficulty).await,
+            (Some(category), Some(difficulty)) => {
+                let level = self.state.difficulty_ids(difficulty).await;
+                let mut ids = self.state.category_ids(category).await;
+                ids.retain(|id| level.contains(id));
+                ids
+            }
+            (None, None) => self.state.active_questions.ids().await,
+        };
+        ids.sort_unstable();
+
+        let mut list = Vec::new();
+        let offset = offset.unwrap_or(0) as usize;
+        let limit = limit.unwrap_or(50).min(MAX_PAGE_SIZE) as usize;
+        for id in ids.into_iter().skip(offset) {
+            if list.len() >= limit {
+                break;
+            }
+            list.extend(self.state.question(id).await);
+        }
+        list
+    }
+
+    async fn categories(&self) -> Vec<String> {
+        self.state.by_category.indices().await.expect("Failed to read")
+    }
+
+    async fn retired_questions(&self) -> Vec<u64> {
+        self.state.retired_questions.indices().await.expect("Failed to read")
+    }
+
+    // Pending submissions, oldest first; pass the last id seen as `after`
+    async fn moderation_queue(&self, after: Option<u64>, limit: Option<u64>) -> Vec<Submission> {
+        let mut ids = self.state.pending_submissions.indices().await.expect("Failed to read");
+        ids.sort_unstable();
+
+        let limit = limit.unwrap_or(50).min(MAX_PAGE_SIZE) as usize;
+        let mut list = Vec::new();
+        for id in ids.into_iter().filter(|id| after.is_none_or(|after| *id > after)).take(limit) {
+            list.extend(self.state.submissions.get(&id).await.expect("Failed to read"));
+        }
+        list
+    }
+
+    async fn submission(&self, id: u64) -> Option<Submission> {
+        self.state.submissions.get(&id).await.expect("Failed to read")
+    }
+
+    async fn moderators(&self) -> Vec<AccountOwner> {
+        self.state.moderators.indices().await.expect("Failed to read")
+    }
+
+    // Questions still waiting to be moved out of the legacy bank
+    async fn unmigrated_questions(&self) -> u64 {
+        self.state.question_bank.get().len() as u64
+    }
+}
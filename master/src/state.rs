@@ -1,13 +1,224 @@
-use linera_sdk::views::{RegisterView, RootView, ViewStorageContext};
-use abi::trivia::Question;
-use linera_sdk::linera_base_types::{Amount, AccountOwner};
+use linera_sdk::views::{
+    // The SDK's aliases fix the context, which a nested view takes as a parameter
+    linera_views::{context::Context, map_view::MapView as GenericMapView, register_view::RegisterView as GenericRegisterView},
+    CollectionView, MapView, RegisterView, RootView, SetView, View, ViewStorageContext,
+};
+use abi::trivia::{Question, QuestionInput};
+use async_graphql::{Enum, SimpleObject};
+use linera_sdk::linera_base_types::{Amount, AccountOwner, ApplicationId, ChainId, Timestamp};
 use serde::{Deserialize, Serialize};
 
 #[derive(RootView, async_graphql::SimpleObject)]
 #[view(context = ViewStorageContext)]
 pub struct MasterState {
+    // Legacy storage, emptied by MigrateQuestionBank; new questions never go here
     pub question_bank: RegisterView<Vec<Question>>,
     pub next_question_id: RegisterView<u64>,
+    // Retired questions stay in the bank for past games but are never selected
+    pub retired_questions: SetView<u64>,
     pub treasury: RegisterView<Amount>,
     pub admin: RegisterView<Option<AccountOwner>>,
-}
\ No newline at end of file
+
+    // Every question ever added, by ID
+    pub questions: MapView<u64, Question>,
+    // Indexes over active questions only
+    #[graphql(skip)]
+    pub active_questions: QuestionSet<ViewStorageContext>,
+    #[graphql(skip)]
+    pub by_category: CollectionView<String, QuestionSet<ViewStorageContext>>,
+    #[graphql(skip)]
+    pub by_difficulty: CollectionView<u8, QuestionSet<ViewStorageContext>>,
+
+    // Questions each play chain was sent lately, newest last
+    pub recent_questions: MapView<ChainId, Vec<u64>>,
+
+    // Community submissions and who may review them
+    pub moderators: SetView<AccountOwner>,
+    pub submissions: MapView<u64, Submission>,
+    pub next_submission_id: RegisterView<u64>,
+    pub pending_submissions: SetView<u64>,
+    // How many of each player's submissions are still pending
+    pub pending_by_submitter: MapView<AccountOwner, u32>,
+}
+
+/// Question IDs packed into positions `0..len`, so a draw reads the one at a random position
+/// instead of the whole set. Removing an ID moves the last one into its place
+#[derive(View)]
+pub struct QuestionSet<C> {
+    ids: GenericMapView<C, u64, u64>,
+    positions: GenericMapView<C, u64, u64>,
+    len: GenericRegisterView<C, u64>,
+}
+
+impl<C: Context> QuestionSet<C> {
+    pub fn len(&self) -> u64 {
+        *self.len.get()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub async fn contains(&self, id: u64) -> bool {
+        self.positions.contains_key(&id).await.expect("Failed to read question set")
+    }
+
+    pub async fn at(&self, position: u64) -> Option<u64> {
+        self.ids.get(&position).await.expect("Failed to read question set")
+    }
+
+    /// Every ID, in no particular order
+    pub async fn ids(&self) -> Vec<u64> {
+        let mut ids = Vec::new();
+        self.ids.for_each_index_value(|_, id| {
+            ids.push(*id);
+            Ok(())
+        }).await.expect("Failed to read question set");
+        ids
+    }
+
+    pub async fn insert(&mut self, id: u64) {
+        if self.contains(id).await {
+            return;
+        }
+        let position = self.len();
+        self.ids.insert(&position, id).expect("Failed to store question set");
+        self.positions.insert(&id, position).expect("Failed to store question set");
+        self.len.set(position + 1);
+    }
+
+    pub async fn remove(&mut self, id: u64) {
+        let Some(position) = self.positions.get(&id).await.expect("Failed to read question set") else {
+            return;
+        };
+        let last = self.len() - 1;
+        if position != last {
+            let moved = self.at(last).await.expect("Positions are dense");
+            self.ids.insert(&position, moved).expect("Failed to store question set");
+            self.positions.insert(&moved, position).expect("Failed to store question set");
+        }
+        self.ids.remove(&last).expect("Failed to store question set");
+        self.positions.remove(&id).expect("Failed to store question set");
+        self.len.set(last);
+    }
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, PartialEq, Eq, Enum)]
+pub enum SubmissionStatus {
+    Pending,
+    Approved,
+    Rejected,
+}
+
+/// A player's question waiting for, or past, moderation
+#[derive(Clone, Debug, Deserialize, Serialize, SimpleObject)]
+pub struct Submission {
+    pub id: u64,
+    pub submitter: AccountOwner,
+    pub submitter_chain: ChainId,
+    // Trivia on the submitter's chain, told when the question is approved
+    pub submitter_app: ApplicationId,
+    pub question: QuestionInput,
+    pub status: SubmissionStatus,
+    pub submitted_at: Timestamp,
+    pub reviewed_by: Option<AccountOwner>,
+    pub reason: Option<String>, // Why it was rejected
+    pub question_id: Option<u64>, // Set once approved
+}
+
+impl MasterState {
+    pub async fn question(&self, id: u64) -> Option<Question> {
+        self.questions.get(&id).await.expect("Failed to read question")
+    }
+
+    pub async fn is_active(&self, id: u64) -> bool {
+        self.active_questions.contains(id).await
+    }
+
+    pub async fn category_ids(&self, category: &String) -> Vec<u64> {
+        match self.by_category.try_load_entry(category).await.expect("Failed to load category") {
+            Some(set) => set.ids().await,
+            None => Vec::new(),
+        }
+    }
+
+    pub async fn difficulty_ids(&self, difficulty: u8) -> Vec<u64> {
+        match self.by_difficulty.try_load_entry(&difficulty).await.expect("Failed to load difficulty") {
+            Some(set) => set.ids().await,
+            None => Vec::new(),
+        }
+    }
+
+    pub fn insert_question(&mut self, question: Question) {
+        let id = question.id;
+        self.questions.insert(&id, question).expect("Failed to store question");
+    }
+
+    pub async fn index_question(&mut self, question: &Question) {
+        self.active_questions.insert(question.id).await;
+        self.by_category.load_entry_mut(&question.category).await
+            .expect("Failed to load category")
+            .insert(question.id)
+            .await;
+        self.by_difficulty.load_entry_mut(&question.difficulty).await
+            .expect("Failed to load difficulty")
+            .insert(question.id)
+            .await;
+    }
+
+    pub async fn unindex_question(&mut self, question: &Question) {
+        self.active_questions.remove(question.id).await;
+        self.by_category.load_entry_mut(&question.category).await
+            .expect("Failed to load category")
+            .remove(question.id)
+            .await;
+        self.by_difficulty.load_entry_mut(&question.difficulty).await
+            .expect("Failed to load difficulty")
+            .remove(question.id)
+            .await;
+    }
+
+    /// Hands out the next question ID, never one still waiting in the legacy Vec
+    pub fn allocate_question_id(&mut self) -> u64 {
+        let legacy_next = self.question_bank.get().iter().map(|q| q.id + 1).max().unwrap_or(0);
+        let id = (*self.next_question_id.get()).max(legacy_next);
+        self.next_question_id.set(id + 1);
+        id
+    }
+
+    /// Moves up to `batch` questions out of the legacy Vec; returns how many are left.
+    /// A legacy question whose ID was already given to another one is renumbered, and stays active
+    /// since any retirement of that ID was meant for the other question.
+    pub async fn migrate_question_bank(&mut self, batch: usize) -> usize {
+        let legacy = self.question_bank.get_mut();
+        let keep = legacy.len().saturating_sub(batch);
+        let moved = legacy.split_off(keep);
+        let left = legacy.len();
+
+        // Seed from every legacy ID, including those still left, before renumbering any
+        let legacy_next = moved.iter().chain(self.question_bank.get()).map(|q| q.id + 1).max().unwrap_or(0);
+        if legacy_next > *self.next_question_id.get() {
+            self.next_question_id.set(legacy_next);
+        }
+
+        for mut question in moved {
+            let taken = self.questions.contains_key(&question.id).await.expect("Failed to read questions");
+            let retired = if taken {
+                question.id = self.allocate_question_id();
+                false
+            } else {
+                self.retired_questions.contains(&question.id).await.expect("Failed to read retired questions")
+            };
+            if !retired {
+                // Older than the bank's rules and may be unplayable, so never select it
+                if question.validate().is_err() {
+                    self.retired_questions.insert(&question.id).expect("Failed to retire question");
+                } else {
+                    self.index_question(&question).await;
+                }
+            }
+            self.insert_question(question);
+        }
+        left
+    }
+}
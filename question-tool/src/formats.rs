@@ -0,0 +1,341 @@
+use std::collections::HashMap;
+use std::fs;
+use std::io::Write;
+use std::path::Path;
+
+use abi::trivia::{Question, QuestionInput};
+use anyhow::{bail, Context, Result};
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum Format {
+    /// Array of questions: text, choices, correct_idx, category, difficulty
+    Json,
+    /// Spreadsheet export: text, category, difficulty, correct (1-based), choice_1..choice_6
+    Csv,
+    /// Open Trivia DB API response or dump
+    Opentdb,
+}
+
+/// A question read from a file, with where it came from for error messages
+pub struct Record {
+    pub source: String,
+    pub input: QuestionInput,
+}
+
+/// One row of the spreadsheet format, used for both import and export
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct CsvRow {
+    #[serde(default)]
+    pub id: Option<u64>,
+    pub text: String,
+    pub category: String,
+    pub difficulty: u8,
+    pub correct: u8,
+    pub choice_1: Option<String>,
+    pub choice_2: Option<String>,
+    pub choice_3: Option<String>,
+    pub choice_4: Option<String>,
+    pub choice_5: Option<String>,
+    pub choice_6: Option<String>,
+}
+
+impl CsvRow {
+    fn into_input(self) -> QuestionInput {
+        // `correct` names a column, so find where that column lands once blank cells are dropped.
+        // 0, a column past the end or a blank one are not valid answers; let validation report them.
+        let column = self.correct.checked_sub(1).map(usize::from);
+        let mut correct_idx = u8::MAX;
+        let mut choices = Vec::new();
+        let cells = [self.choice_1, self.choice_2, self.choice_3, self.choice_4, self.choice_5, self.choice_6];
+        for (i, cell) in cells.into_iter().enumerate() {
+            let Some(choice) = cell.filter(|c| !c.trim().is_empty()) else { continue };
+            if column == Some(i) {
+                correct_idx = choices.len() as u8;
+            }
+            choices.push(choice);
+        }
+
+        QuestionInput {
+            text: self.text,
+            choices,
+            correct_idx,
+            category: self.category,
+            difficulty: self.difficulty,
+        }
+    }
+
+    pub fn from_question(question: &Question) -> Self {
+        let choice = |i: usize| question.choices.get(i).cloned();
+        CsvRow {
+            id: Some(question.id),
+            text: question.text.clone(),
+            category: question.category.clone(),
+            difficulty: question.difficulty,
+            correct: question.correct_idx + 1,
+            choice_1: choice(0),
+            choice_2: choice(1),
+            choice_3: choice(2),
+            choice_4: choice(3),
+            choice_5: choice(4),
+            choice_6: choice(5),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum OpenTdbFile {
+    Response { results: Vec<OpenTdbQuestion> },
+    Dump(Vec<OpenTdbQuestion>),
+}
+
+#[derive(Deserialize)]
+struct OpenTdbQuestion {
+    #[serde(rename = "type")]
+    kind: String,
+    difficulty: String,
+    category: String,
+    question: String,
+    correct_answer: String,
+    incorrect_answers: Vec<String>,
+}
+
+impl OpenTdbQuestion {
+    // Master shuffles choices for every game, so the stored order doesn't matter
+    fn into_input(self) -> QuestionInput {
+        let decode = |s: &str| html_escape::decode_html_entities(s).into_owned();
+        let correct = decode(&self.correct_answer);
+
+        let choices: Vec<String> = if self.kind == "boolean" {
+            vec!["True".to_string(), "False".to_string()]
+        } else {
+            std::iter::once(correct.clone())
+                .chain(self.incorrect_answers.iter().map(|a| decode(a)))
+                .collect()
+        };
+        let correct_idx = choices.iter().position(|c| *c == correct).unwrap_or(usize::from(u8::MAX)) as u8;
+
+        QuestionInput {
+            text: decode(&self.question),
+            choices,
+            correct_idx,
+            category: decode(&self.category),
+            difficulty: match self.difficulty.as_str() {
+                "easy" => 1,
+                "medium" => 3,
+                "hard" => 5,
+                _ => 0,
+            },
+        }
+    }
+}
+
+/// Guesses the format from the extension, and tells Open Trivia DB files from ours by their fields
+pub fn detect(path: &Path, contents: &str) -> Format {
+    if path.extension().is_some_and(|e| e.eq_ignore_ascii_case("csv")) {
+        Format::Csv
+    } else if contents.contains("\"correct_answer\"") {
+        Format::Opentdb
+    } else {
+        Format::Json
+    }
+}
+
+/// Reads every question in a file; rows that can't even be parsed are returned as errors
+pub fn read(path: &Path, format: Option<Format>) -> Result<(Vec<Record>, Vec<String>)> {
+    let contents = fs::read_to_string(path).with_context(|| format!("Failed to read {}", path.display()))?;
+    let name = path.display();
+    let mut records = Vec::new();
+    let mut errors = Vec::new();
+
+    match format.unwrap_or_else(|| detect(path, &contents)) {
+        Format::Json => {
+            let inputs: Vec<QuestionInput> = serde_json::from_str(&contents)
+                .with_context(|| format!("{name} is not a JSON array of questions"))?;
+            for (i, input) in inputs.into_iter().enumerate() {
+                records.push(Record { source: format!("{name}[{i}]"), input });
+            }
+        }
+
+        Format::Csv => {
+            let mut reader = csv::Reader::from_reader(contents.as_bytes());
+            for (i, row) in reader.deserialize::<CsvRow>().enumerate() {
+                // Line 1 is the header
+                let source = format!("{name}:{}", i + 2);
+                match row {
+                    Ok(row) => records.push(Record { source, input: row.into_input() }),
+                    Err(error) => errors.push(format!("{source}: {error}")),
+                }
+            }
+        }
+
+        Format::Opentdb => {
+            let results = match serde_json::from_str(&contents)
+                .with_context(|| format!("{name} is not an Open Trivia DB file"))?
+            {
+                OpenTdbFile::Response { results } => results,
+                OpenTdbFile::Dump(results) => results,
+            };
+            for (i, question) in results.into_iter().enumerate() {
+                records.push(Record { source: format!("{name}[{i}]"), input: question.into_input() });
+            }
+        }
+    }
+
+    Ok((records, errors))
+}
+
+/// Questions are duplicates when their text matches, ignoring case, spacing and punctuation
+pub fn dedup_key(input: &QuestionInput) -> String {
+    input.text
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|w| !w.is_empty())
+        .map(str::to_lowercase)
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Keeps the first of each duplicate; returns the kept records and a note for each dropped one
+pub fn dedup(records: Vec<Record>) -> (Vec<Record>, Vec<String>) {
+    let mut seen: HashMap<String, String> = HashMap::new();
+    let mut kept = Vec::new();
+    let mut dropped = Vec::new();
+
+    for record in records {
+        let key = dedup_key(&record.input);
+        match seen.get(&key) {
+            Some(first) => dropped.push(format!("{}: duplicate of {first}", record.source)),
+            None => {
+                seen.insert(key, record.source.clone());
+                kept.push(record);
+            }
+        }
+    }
+    (kept, dropped)
+}
+
+pub fn write_json(out: &mut dyn Write, questions: &[Question]) -> Result<()> {
+    serde_json::to_writer_pretty(&mut *out, questions)?;
+    writeln!(out)?;
+    Ok(())
+}
+
+pub fn write_csv(out: &mut dyn Write, questions: &[Question]) -> Result<()> {
+    let mut writer = csv::Writer::from_writer(out);
+    for question in questions {
+        if question.choices.len() > QuestionInput::MAX_CHOICES {
+            bail!("Question {} has more choices than the CSV format holds", question.id);
+        }
+        writer.serialize(CsvRow::from_question(question))?;
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse_csv(contents: &str) -> Vec<QuestionInput> {
+        csv::Reader::from_reader(contents.as_bytes())
+            .deserialize::<CsvRow>()
+            .map(|row| row.expect("Row should parse").into_input())
+            .collect()
+    }
+
+    fn record(source: &str, text: &str) -> Record {
+        Record {
+            source: source.to_string(),
+            input: QuestionInput {
+                text: text.to_string(),
+                choices: vec!["a".to_string(), "b".to_string()],
+                correct_idx: 0,
+                category: "General".to_string(),
+                difficulty: 1,
+            },
+        }
+    }
+
+    const HEADER: &str = "text,category,difficulty,correct,choice_1,choice_2,choice_3,choice_4,choice_5,choice_6\n";
+
+    #[test]
+    fn csv_correct_column_survives_blank_cells() {
+        let inputs = parse_csv(&format!("{HEADER}Capital of France?,Geography,1,3,Rome,,Paris,Berlin,,\n"));
+
+        assert_eq!(inputs[0].choices, ["Rome", "Paris", "Berlin"]);
+        assert_eq!(inputs[0].correct_idx, 1);
+        assert!(inputs[0].validate().is_ok());
+    }
+
+    #[test]
+    fn csv_rejects_blank_or_missing_correct_column() {
+        let inputs = parse_csv(&format!(
+            "{HEADER}Blank answer?,General,1,2,Yes,,No,,,\nNo answer?,General,1,0,Yes,No,,,,\nPast the end?,General,1,5,Yes,No,,,,\n"
+        ));
+
+        for input in inputs {
+            assert_eq!(input.correct_idx, u8::MAX, "{}", input.text);
+            assert!(input.validate().is_err(), "{}", input.text);
+        }
+    }
+
+    #[test]
+    fn csv_round_trips_exported_questions() {
+        let question = Question {
+            id: 7,
+            text: "2 + 2?".to_string(),
+            choices: vec!["3".to_string(), "4".to_string(), "5".to_string()],
+            correct_idx: 1,
+            category: "Math".to_string(),
+            difficulty: 2,
+        };
+        let mut out = Vec::new();
+        write_csv(&mut out, std::slice::from_ref(&question)).unwrap();
+
+        let inputs = parse_csv(std::str::from_utf8(&out).unwrap());
+        assert_eq!(inputs[0].choices, question.choices);
+        assert_eq!(inputs[0].correct_idx, question.correct_idx);
+    }
+
+    #[test]
+    fn opentdb_decodes_entities_and_keeps_the_answer() {
+        let file: OpenTdbFile = serde_json::from_str(
+            r#"{"response_code":0,"results":[
+                {"type":"multiple","difficulty":"hard","category":"Science &amp; Nature",
+                 "question":"Who wrote &quot;On the Origin of Species&quot;?",
+                 "correct_answer":"Darwin","incorrect_answers":["Wallace","Lamarck","Mendel"]},
+                {"type":"boolean","difficulty":"easy","category":"General",
+                 "question":"The sky is blue.","correct_answer":"False","incorrect_answers":["True"]}
+            ]}"#,
+        )
+        .unwrap();
+        let OpenTdbFile::Response { results } = file else { panic!("Expected an API response") };
+        let inputs: Vec<QuestionInput> = results.into_iter().map(OpenTdbQuestion::into_input).collect();
+
+        assert_eq!(inputs[0].text, "Who wrote \"On the Origin of Species\"?");
+        assert_eq!(inputs[0].category, "Science & Nature");
+        assert_eq!(inputs[0].choices[usize::from(inputs[0].correct_idx)], "Darwin");
+        assert_eq!(inputs[0].difficulty, 5);
+
+        assert_eq!(inputs[1].choices, ["True", "False"]);
+        assert_eq!(inputs[1].correct_idx, 1);
+        assert_eq!(inputs[1].difficulty, 1);
+    }
+
+    #[test]
+    fn dedup_ignores_case_spacing_and_punctuation() {
+        let records = vec![
+            record("a.json[0]", "What is the capital of France?"),
+            record("a.json[1]", "what is the  capital of france"),
+            record("b.csv:2", "What's the capital of France?"),
+            record("b.csv:3", "What is the capital of Spain?"),
+        ];
+        let (kept, dropped) = dedup(records);
+
+        let kept: Vec<&str> = kept.iter().map(|r| r.source.as_str()).collect();
+        assert_eq!(kept, ["a.json[0]", "b.csv:2", "b.csv:3"]);
+        assert_eq!(dropped, ["a.json[1]: duplicate of a.json[0]"]);
+    }
+}
@@ -0,0 +1,168 @@
+//! Validates, deduplicates and converts question files for the master question bank,
+//! and exports the current bank through master's GraphQL service.
+
+mod formats;
+mod master;
+
+use std::fs::{self, File};
+use std::io::{self, Write};
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+use abi::trivia::QuestionInput;
+use anyhow::{Context, Result};
+use clap::{Parser, Subcommand, ValueEnum};
+
+use formats::{Format, Record};
+
+#[derive(Parser)]
+#[command(about = "Question bank import/export for Trivia Battle")]
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Check question files against the master bank rules
+    Validate {
+        files: Vec<PathBuf>,
+        /// Input format; guessed from each file when omitted
+        #[arg(long, value_enum)]
+        format: Option<Format>,
+    },
+
+    /// Validate, deduplicate and write batched AddQuestions requests
+    Import {
+        files: Vec<PathBuf>,
+        #[arg(long, value_enum)]
+        format: Option<Format>,
+        /// Questions per AddQuestions request
+        #[arg(long, default_value_t = 50)]
+        batch_size: usize,
+        /// Directory for batch-NNNN.json files; one request per line on stdout when omitted
+        #[arg(long)]
+        out: Option<PathBuf>,
+        /// Leave out invalid questions instead of refusing to import
+        #[arg(long)]
+        skip_invalid: bool,
+    },
+
+    /// Dump the active questions of a master application
+    Export {
+        /// GraphQL endpoint of the master application, e.g.
+        /// http://localhost:8080/chains/<chain>/applications/<app>
+        #[arg(long)]
+        url: String,
+        #[arg(long, value_enum, default_value = "json")]
+        format: ExportFormat,
+        /// Output file; stdout when omitted
+        #[arg(long)]
+        out: Option<PathBuf>,
+    },
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum ExportFormat {
+    Json,
+    Csv,
+}
+
+fn main() -> ExitCode {
+    match run(Cli::parse()) {
+        Ok(true) => ExitCode::SUCCESS,
+        Ok(false) => ExitCode::FAILURE,
+        Err(error) => {
+            eprintln!("error: {error:#}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+/// Returns whether every question was accepted
+fn run(cli: Cli) -> Result<bool> {
+    match cli.command {
+        Command::Validate { files, format } => {
+            let (valid, invalid) = load(&files, format)?;
+            eprintln!("{} valid, {invalid} invalid", valid.len());
+            Ok(invalid == 0)
+        }
+
+        Command::Import { files, format, batch_size, out, skip_invalid } => {
+            let (valid, invalid) = load(&files, format)?;
+            if invalid > 0 && !skip_invalid {
+                eprintln!("{invalid} invalid questions; fix them or pass --skip-invalid");
+                return Ok(false);
+            }
+
+            let inputs: Vec<QuestionInput> = valid.into_iter().map(|r| r.input).collect();
+            let batches: Vec<&[QuestionInput]> = inputs.chunks(batch_size.max(1)).collect();
+            match out {
+                Some(dir) => {
+                    fs::create_dir_all(&dir).with_context(|| format!("Failed to create {}", dir.display()))?;
+                    for (i, batch) in batches.iter().enumerate() {
+                        let path = dir.join(format!("batch-{:04}.json", i + 1));
+                        let request = master::add_questions_request(batch);
+                        fs::write(&path, serde_json::to_string_pretty(&request)?)
+                            .with_context(|| format!("Failed to write {}", path.display()))?;
+                    }
+                }
+                None => {
+                    let mut stdout = io::stdout().lock();
+                    for batch in &batches {
+                        writeln!(stdout, "{}", master::add_questions_request(batch))?;
+                    }
+                }
+            }
+
+            eprintln!("{} questions in {} batches", inputs.len(), batches.len());
+            Ok(invalid == 0)
+        }
+
+        Command::Export { url, format, out } => {
+            let questions = master::export(&url)?;
+            let mut writer: Box<dyn Write> = match &out {
+                Some(path) => Box::new(File::create(path).with_context(|| format!("Failed to create {}", path.display()))?),
+                None => Box::new(io::stdout().lock()),
+            };
+            match format {
+                ExportFormat::Json => formats::write_json(&mut writer, &questions)?,
+                ExportFormat::Csv => formats::write_csv(&mut writer, &questions)?,
+            }
+
+            eprintln!("{} questions exported", questions.len());
+            Ok(true)
+        }
+    }
+}
+
+/// Reads, validates and deduplicates all files, reporting problems on stderr.
+/// Returns the questions worth importing and how many were invalid.
+fn load(files: &[PathBuf], format: Option<Format>) -> Result<(Vec<Record>, usize)> {
+    let mut records = Vec::new();
+    let mut invalid = 0;
+
+    for path in files {
+        let (read, errors) = formats::read(path, format)?;
+        invalid += errors.len();
+        for error in errors {
+            eprintln!("{error}");
+        }
+
+        for record in read {
+            match record.input.validate() {
+                Ok(()) => records.push(record),
+                Err(error) => {
+                    eprintln!("{}: {error}", record.source);
+                    invalid += 1;
+                }
+            }
+        }
+    }
+
+    let (records, duplicates) = formats::dedup(records);
+    for duplicate in duplicates {
+        eprintln!("{duplicate} (skipped)");
+    }
+    Ok((records, invalid))
+}
@@ -0,0 +1,90 @@
+use abi::trivia::{Question, QuestionInput};
+use anyhow::{bail, Context, Result};
+use serde::Deserialize;
+use serde_json::{json, Value};
+
+/// Largest page the master service returns
+const PAGE_SIZE: u64 = 200;
+
+const ADD_QUESTIONS: &str = "mutation AddQuestions($questions: [QuestionInput!]!) {\n  addQuestions(questions: $questions)\n}";
+
+const QUESTIONS: &str = "query Questions($offset: Int, $limit: Int) {\n  questions(offset: $offset, limit: $limit) { id text choices correctIdx category difficulty }\n}";
+
+/// A GraphQL request body for master's `addQuestions` mutation
+pub fn add_questions_request(questions: &[QuestionInput]) -> Value {
+    let questions: Vec<Value> = questions
+        .iter()
+        .map(|q| {
+            json!({
+                "text": q.text,
+                "choices": q.choices,
+                "correctIdx": q.correct_idx,
+                "category": q.category,
+                "difficulty": q.difficulty,
+            })
+        })
+        .collect();
+
+    json!({
+        "query": ADD_QUESTIONS,
+        "variables": { "questions": questions },
+    })
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct GraphQlQuestion {
+    id: u64,
+    text: String,
+    choices: Vec<String>,
+    correct_idx: u8,
+    category: String,
+    difficulty: u8,
+}
+
+#[derive(Deserialize)]
+struct QuestionsData {
+    questions: Vec<GraphQlQuestion>,
+}
+
+#[derive(Deserialize)]
+struct GraphQlResponse {
+    data: Option<QuestionsData>,
+    #[serde(default)]
+    errors: Vec<Value>,
+}
+
+/// Reads every active question from master's GraphQL service, a page at a time
+pub fn export(url: &str) -> Result<Vec<Question>> {
+    let mut questions = Vec::new();
+    loop {
+        let body = json!({
+            "query": QUESTIONS,
+            "variables": { "offset": questions.len(), "limit": PAGE_SIZE },
+        });
+        let response: GraphQlResponse = ureq::post(url)
+            .send_json(&body)
+            .with_context(|| format!("Failed to query {url}"))?
+            .body_mut()
+            .read_json()
+            .context("Master service returned something other than GraphQL")?;
+
+        if !response.errors.is_empty() {
+            bail!("Master service returned errors: {}", Value::from(response.errors));
+        }
+        let page = response.data.context("Master service returned no data")?.questions;
+        let last_page = (page.len() as u64) < PAGE_SIZE;
+
+        questions.extend(page.into_iter().map(|q| Question {
+            id: q.id,
+            text: q.text,
+            choices: q.choices,
+            correct_idx: q.correct_idx,
+            category: q.category,
+            difficulty: q.difficulty,
+        }));
+        if last_page {
+            return Ok(questions);
+        }
+    }
+}
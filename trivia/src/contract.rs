@@ -7,13 +7,26 @@ use async_trait::async_trait;
 use linera_sdk::{
     views::View,
     Contract, ContractRuntime,
-    linera_base_types::{AccountOwner, Amount, Timestamp, ChainId},
+    linera_base_types::{AccountOwner, Amount, Timestamp, ChainId, CryptoHash, TimeDelta},
 };
 use state::TriviaState;
-use game::{TriviaBattle, PlayerInBattle, BattleStatus};
-use crate::{TriviaOperation, TriviaMessage, TriviaParameters};
+use game::{GameResult, TriviaBattle, PlayerInBattle, BattleStatus};
+use crate::{
+    AnswerMode, AnswerReveal, RoomRules, TieBreak, TriviaOperation, TriviaMessage, TriviaParameters,
+};
 use bankroll::BankrollOperation;
 use abi::leaderboard::LeaderboardEntry;
+use abi::management::RoomInfo;
+use abi::master::MasterOperation;
+use abi::trivia::{Payout, PlayerScore, Question};
+use std::cmp::Ordering;
+
+/// Seats in a room opened by matchmaking when nothing fits
+const QUICK_MATCH_PLAYERS: u8 = 4;
+
+/// How long to wait for master to send questions before asking again
+const QUESTION_REQUEST_TIMEOUT_SECONDS: u64 = 60;
+const MAX_QUESTION_REQUESTS: u8 = 3;
 
 pub struct TriviaContract {
     runtime: ContractRuntime<Self>,
@@ -43,6 +56,25 @@ impl Contract for TriviaContract {
     async fn instantiate(&mut self, _arg: ()) {}
 
     async fn execute_operation(&mut self, op: TriviaOperation) {
+        // Operations from the master app on this chain rather than from a signer
+        if matches!(op, TriviaOperation::QuestionApproved { .. } | TriviaOperation::ReceiveQuestions { .. }) {
+            let master_app = self.runtime.application_parameters().master_app;
+            if self.runtime.authenticated_caller_id() != Some(master_app.forget_abi()) {
+                return;
+            }
+            match op {
+                // User Chain: one of our submissions made it into the bank
+                TriviaOperation::QuestionApproved { .. } => {
+                    self.state.profile.get_mut().questions_approved += 1;
+                }
+                TriviaOperation::ReceiveQuestions { game_id, question_ids, questions, matched } => {
+                    self.receive_questions(game_id, question_ids, questions, matched);
+                }
+                _ => {}
+            }
+            return;
+        }
+
         let signer = match self.runtime.authenticated_signer() {
             Some(s) => s,
             None => return,
@@ -55,72 +87,41 @@ impl Contract for TriviaContract {
                 bet_amount,
                 password,
                 display_name,
+                rules,
             } => {
-                let mut battle = TriviaBattle {
-                    room_name: name,
-                    owner: signer,
-                    max_players,
-                    bet_amount,
-                    password,
-                    players: vec![],
-                    question_ids: vec![],
-                    full_questions: vec![],
-                    current_question_index: 0,
-                    current_question_start_time: None,
-                    question_timeout_seconds: 30,
-                    status: BattleStatus::Waiting,
-                    pot: Amount::ZERO,
-                    start_time: None,
-                };
+                if !rules.payout.is_valid() || !rules.scoring.is_valid() || !rules.questions.is_valid() {
+                    return;
+                }
 
-                // Owner joins with real display name
-                battle.players.push(PlayerInBattle {
-                    owner: signer,
-                    name: display_name,
-                    score: 0,
-                    has_answered_current: false,
-                    last_answer_time: None,
-                });
+                self.open_room(signer, name, max_players, bet_amount, password, display_name, rules)
+                    .await;
+            }
 
-                self.state.battle.set(battle);
+            TriviaOperation::FindPlayChain {
+                display_name,
+                min_bet,
+                max_bet,
+            } => {
+                let master = self.runtime.application_parameters().master_chain;
+                self.runtime
+                    .prepare_message(TriviaMessage::FindPlayChainRequest {
+                        player: signer,
+                        display_name,
+                        min_bet,
+                        max_bet,
+                    })
+                    .with_authentication()
+                    .send_to(master);
             }
 
             TriviaOperation::RequestJoinRoom {
-                room_chain: _,
+                room_chain,
                 password,
                 display_name,
+                stake,
             } => {
-                let mut battle = self.state.battle.get_mut();
-
-                if battle.status != BattleStatus::Waiting {
-                    return;
-                }
-
-                // Password check
-                if let Some(ref pwd) = battle.password {
-                    if Some(pwd.as_str()) != password.as_deref() {
-                        return;
-                    }
-                }
-
-                // Room full?
-                if battle.players.len() >= battle.max_players as usize {
-                    return;
-                }
-
-                // Player joins with real display name
-                battle.players.push(PlayerInBattle {
-                    owner: signer,
-                    name: display_name,
-                    score: 0,
-                    has_answered_current: false,
-                    last_answer_time: None,
-                });
-
-                self.broadcast(TriviaMessage::PlayerJoined {
-                    player: signer,
-                    name: display_name.clone(),
-                });
+                // The play chain validates the request and answers with JoinResult
+                self.request_join(signer, room_chain, password, display_name, stake);
             }
 
             TriviaOperation::StartGame {} => {
@@ -136,33 +137,39 @@ impl Contract for TriviaContract {
 
                 let bet = battle.bet_amount;
 
-                // === REAL BET COLLECTION ===
+                battle.game_id = *self.state.next_game_id.get();
+                self.state.next_game_id.set(battle.game_id + 1);
+
+                // Lock every stake in escrow; bankroll fails the start if anyone is short
                 if !bet.is_zero() {
                     let bankroll_id = self.runtime.application_parameters().bankroll_app;
-
-                    for player in &battle.players {
-                        let debt_op = BankrollOperation::NotifyDebt {
-                            amount: bet,
-                            target_chain: self.runtime.chain_id(),
-                        };
-                        self.runtime.call_application(true, bankroll_id, &debt_op);
-                    }
-
-                    battle.pot = bet.checked_mul(battle.players.len() as u128).unwrap_or(Amount::MAX);
+                    let lock_op = BankrollOperation::LockStakes {
+                        game_id: battle.game_id,
+                        players: battle.players.iter().map(|p| p.owner).collect(),
+                        stake: bet,
+                    };
+                    self.runtime.call_application(true, bankroll_id, &lock_op);
+
+                    battle.pot = bet.saturating_mul(battle.players.len() as u128);
                 }
 
                 battle.status = BattleStatus::InProgress;
                 battle.start_time = Some(self.runtime.system_time());
 
-                let master = self.runtime.application_parameters().master_chain;
-                self.send_message(master, TriviaMessage::RequestQuestions { count: 10 });
+                self.request_questions(10);
+
+                // Room is no longer joinable
+                self.publish_room_info();
             }
 
             TriviaOperation::Answer { question_index, choice } => {
                 let mut battle = self.state.battle.get_mut();
 
                 if battle.status != BattleStatus::InProgress
+                    || battle.rules.answer_mode != AnswerMode::Direct
+                    || battle.awaiting_questions()
                     || battle.current_question_index != question_index
+                    || !battle.is_contender(&signer)
                 {
                     return;
                 }
@@ -177,20 +184,144 @@ impl Contract for TriviaContract {
                     return;
                 }
 
+                let now = self.runtime.system_time();
                 player.has_answered_current = true;
-                player.last_answer_time = Some(self.runtime.system_time());
+                player.last_answer_time = Some(now);
 
                 let question = &battle.full_questions[question_index as usize];
                 let is_correct = choice == question.correct_idx;
-                let points = if is_correct { 100 } else { 0 };
-                let speed_bonus = 20;
-                player.score += points + speed_bonus;
+                let elapsed = battle
+                    .current_question_start_time
+                    .map_or(TimeDelta::default(), |start| now.delta_since(start));
+                let window = TimeDelta::from_secs(battle.question_timeout_seconds);
+                let points = battle.rules.scoring.points(is_correct, elapsed, window, question.difficulty);
+                let player = &mut battle.players[player_idx];
+                player.score = player.score.saturating_add(points);
+
+                self.broadcast(TriviaMessage::PlayerAnswered {
+                    player: signer,
+                    question_index,
+                    choice,
+                    answered_at: now,
+                });
+
+                self.check_question_timeout_and_advance();
+            }
+
+            TriviaOperation::LeaveRoom {} => {
+                let room_chain = match *self.state.current_room.get() {
+                    Some(chain) => chain,
+                    None => return,
+                };
+                self.state.current_room.set(None);
+
+                self.runtime
+                    .prepare_message(TriviaMessage::LeaveRoomRequest)
+                    .with_authentication()
+                    .send_to(room_chain);
+            }
+
+            // User Chain: master records the signer as the author
+            TriviaOperation::SubmitQuestion { question } => {
+                if question.validate().is_err() {
+                    return;
+                }
+
+                let master_app = self.runtime.application_parameters().master_app;
+                self.runtime.call_application(true, master_app, &MasterOperation::SubmitQuestion { question });
+            }
+
+            // Anyone (a player or a keeper) can push a stalled game past its deadline
+            TriviaOperation::AdvanceQuestion {} => {
+                let battle = self.state.battle.get();
+
+                if battle.status != BattleStatus::InProgress {
+                    return;
+                }
+
+                if battle.awaiting_questions() {
+                    self.retry_or_abort_question_request();
+                } else {
+                    self.check_question_timeout_and_advance();
+                }
+            }
+
+            TriviaOperation::CommitAnswer { question_index, commitment } => {
+                let now = self.runtime.system_time();
+                let battle = self.state.battle.get_mut();
+
+                if battle.status != BattleStatus::InProgress
+                    || battle.rules.answer_mode != AnswerMode::CommitReveal
+                    || battle.awaiting_questions()
+                    || battle.current_question_index != question_index
+                    || battle.reveal_start_time.is_some()
+                    || !battle.is_contender(&signer)
+                {
+                    return;
+                }
+
+                let player = match battle.players.iter_mut().find(|p| p.owner == signer) {
+                    Some(p) => p,
+                    None => return,
+                };
+
+                if player.has_answered_current {
+                    return;
+                }
+
+                player.has_answered_current = true;
+                player.last_answer_time = Some(now);
+                player.commitment = Some(commitment);
+
+                // Only the hash goes out, nothing to copy
+                self.broadcast(TriviaMessage::PlayerCommitted {
+                    player: signer,
+                    question_index,
+                    committed_at: now,
+                });
+
+                self.check_question_timeout_and_advance();
+            }
+
+            TriviaOperation::RevealAnswer { question_index, choice, salt } => {
+                let battle = self.state.battle.get_mut();
+
+                if battle.status != BattleStatus::InProgress
+                    || battle.awaiting_questions()
+                    || battle.current_question_index != question_index
+                    || battle.reveal_start_time.is_none()
+                {
+                    return;
+                }
+
+                let player = match battle.players.iter_mut().find(|p| p.owner == signer) {
+                    Some(p) => p,
+                    None => return,
+                };
+
+                let commitment = match player.commitment {
+                    Some(c) if player.revealed_choice.is_none() => c,
+                    _ => return,
+                };
+
+                let reveal = AnswerReveal {
+                    player: signer,
+                    question_index,
+                    choice,
+                    salt,
+                };
+                if CryptoHash::new(&reveal) != commitment {
+                    return;
+                }
+
+                player.revealed_choice = Some(choice);
+                let answered_at = player.last_answer_time.unwrap_or_default();
 
                 self.broadcast(TriviaMessage::PlayerAnswered {
                     player: signer,
                     question_index,
                     choice,
-                    answered_at: self.runtime.system_time(),
+                    answered_at,
                 });
 
                 self.check_question_timeout_and_advance();
@@ -201,39 +332,203 @@ impl Contract for TriviaContract {
     }
 
     async fn execute_message(&mut self, message: TriviaMessage) {
-        let mut battle = self.state.battle.get_mut();
-
         match message {
-            TriviaMessage::ReceiveQuestions { question_ids, questions } => {
-                if battle.status != BattleStatus::InProgress {
+            // Play Chain: a user chain asks for a seat
+            TriviaMessage::JoinRoomRequest {
+                password,
+                display_name,
+                reward_multiplier,
+                stake,
+            } => {
+                let origin = match self.runtime.message_origin_chain_id() {
+                    Some(chain) => chain,
+                    None => return,
+                };
+
+                let result = match self.runtime.authenticated_signer() {
+                    Some(player) => {
+                        let joined = self
+                            .join_battle(player, origin, display_name, password, reward_multiplier, stake)
+                            .await;
+                        if joined.is_err() {
+                            // No seat, so the stake goes straight back
+                            self.send_funds(player, stake, origin);
+                        }
+                        joined
+                    }
+                    None => Err("Join request is not signed".to_string()),
+                };
+
+                let (success, message) = match result {
+                    Ok(()) => (true, "Joined room".to_string()),
+                    Err(reason) => (false, reason),
+                };
+
+                let room_chain = Some(self.runtime.chain_id());
+                self.send_message(origin, TriviaMessage::JoinResult {
+                    success,
+                    message,
+                    room_chain,
+                });
+            }
+
+            // Play Chain: a player gives up their seat
+            TriviaMessage::LeaveRoomRequest => {
+                if let Some(player) = self.runtime.authenticated_signer() {
+                    self.leave_battle(player);
+                }
+            }
+
+            // User Chain: answer from the play chain
+            TriviaMessage::JoinResult { success, message, room_chain } => {
+                if room_chain != self.runtime.message_origin_chain_id() {
                     return;
                 }
 
-                battle.question_ids = question_ids;
-                battle.full_questions = questions;
-                battle.current_question_index = 0;
-                battle.current_question_start_time = Some(self.runtime.system_time());
+                if success {
+                    self.state.current_room.set(room_chain);
+                } else {
+                    log::info!("Join rejected: {}", message);
+                }
+            }
 
-                for p in &mut battle.players {
-                    p.has_answered_current = false;
-                    p.last_answer_time = None;
+            // Master Chain: keep the room registry up to date
+            TriviaMessage::RoomsData { rooms } => {
+                let origin = match self.runtime.message_origin_chain_id() {
+                    Some(chain) => chain,
+                    None => return,
+                };
+                for room in rooms {
+                    self.upsert_room(origin, room).await;
                 }
+            }
 
-                self.broadcast(TriviaMessage::GameStarted {
-                    question_ids: battle.question_ids.clone(),
-                });
+            // Master Chain: pick an open room for the player
+            TriviaMessage::FindPlayChainRequest {
+                player: _,
+                display_name,
+                min_bet,
+                max_bet,
+            } => {
+                let origin = match self.runtime.message_origin_chain_id() {
+                    Some(chain) => chain,
+                    None => return,
+                };
 
-                self.broadcast(TriviaMessage::NextQuestion {
-                    index: 0,
-                    question_id: battle.question_ids[0],
-                });
+                let room = self.find_open_room(min_bet, max_bet).await;
+                let (room_chain, bet_amount) = match room {
+                    Some(room) => (Some(room.chain_id), room.bet_amount),
+                    None => (None, min_bet),
+                };
+
+                self.runtime
+                    .prepare_message(TriviaMessage::FindPlayChainResult {
+                        room_chain,
+                        display_name,
+                        bet_amount,
+                    })
+                    .with_authentication()
+                    .send_to(origin);
+            }
+
+            // User Chain: the room we are playing in reports how our game went
+            TriviaMessage::GameFinished { score, won, winnings, pot } => {
+                if self.runtime.message_origin_chain_id() != *self.state.current_room.get() {
+                    return;
+                }
+                self.state.profile.get_mut().record_game(score, won, winnings, pot);
+            }
+
+            // User Chain: join the matched room, or host a new one
+            TriviaMessage::FindPlayChainResult {
+                room_chain,
+                display_name,
+                bet_amount,
+            } => {
+                let player = match self.runtime.authenticated_signer() {
+                    Some(player) => player,
+                    None => return,
+                };
+
+                match room_chain {
+                    Some(room_chain) => {
+                        self.request_join(player, room_chain, None, display_name, bet_amount);
+                    }
+                    None => {
+                        self.open_room(
+                            player,
+                            "Quick match".to_string(),
+                            QUICK_MATCH_PLAYERS,
+                            bet_amount,
+                            None,
+                            display_name,
+                            RoomRules::default(),
+                        )
+                        .await;
+                    }
+                }
             }
+
             _ => {}
         }
     }
 }
 
 impl TriviaContract {
+    // Play chain: questions from master, for the current game or its sudden death
+    fn receive_questions(
+        &mut self,
+        game_id: u64,
+        question_ids: Vec<u64>,
+        questions: Vec<Question>,
+        matched: u32,
+    ) {
+        let now = self.runtime.system_time();
+        let battle = self.state.battle.get_mut();
+
+        // Ignore late answers to a retried request, or to an earlier game
+        if battle.status != BattleStatus::InProgress
+            || !battle.awaiting_questions()
+            || battle.game_id != game_id
+        {
+            return;
+        }
+
+        if questions.is_empty() {
+            if battle.full_questions.is_empty() {
+                self.abort_game("No questions available".to_string());
+            } else {
+                battle.sudden_death.clear();
+                self.end_game();
+            }
+            return;
+        }
+
+        // First batch starts the game; later ones are sudden-death questions
+        let first_batch = battle.full_questions.is_empty();
+        battle.question_ids.extend(question_ids);
+        battle.full_questions.extend(questions);
+        battle.questions_matched += matched;
+        battle.current_question_start_time = Some(now);
+        battle.reveal_start_time = None;
+
+        for p in &mut battle.players {
+            p.has_answered_current = false;
+            p.last_answer_time = None;
+            p.commitment = None;
+            p.revealed_choice = None;
+        }
+
+        let index = battle.current_question_index;
+        let question_count = battle.question_ids.len() as u8;
+
+        if first_batch {
+            self.broadcast(TriviaMessage::GameStarted { question_count });
+        }
+
+        self.broadcast(TriviaMessage::NextQuestion { index });
+    }
+
     fn broadcast(&mut self, msg: TriviaMessage) {
         log::info!("Broadcast: {:?}", msg);
     }
@@ -242,79 +537,523 @@ impl TriviaContract {
         self.runtime.prepare_message(msg).send_to(destination);
     }
 
-    fn check_question_timeout_and_advance(&mut self) {
-        let mut battle = self.state.battle.get_mut();
+    #[allow(clippy::too_many_arguments)]
+    async fn open_room(
+        &mut self,
+        owner: AccountOwner,
+        name: String,
+        max_players: u8,
+        bet_amount: Amount,
+        password: Option<String>,
+        display_name: String,
+        rules: RoomRules,
+    ) {
+        // A chain hosts one battle at a time; replacing a live one would strand its pot
+        let current = self.state.battle.get();
+        if current.status == BattleStatus::InProgress {
+            log::info!("Room not opened: a game is in progress on this chain");
+            return;
+        }
+
+        // Joined players' stakes are already here, so a waiting room stays until they leave
+        if current.status == BattleStatus::Waiting && current.players.iter().any(|p| p.owner != owner) {
+            log::info!("Room not opened: other players are waiting in the current room");
+            return;
+        }
+
+        let mut battle = TriviaBattle {
+            game_id: 0,
+            room_name: name,
+            owner,
+            max_players,
+            bet_amount,
+            password,
+            players: vec![],
+            question_ids: vec![],
+            full_questions: vec![],
+            questions_matched: 0,
+            current_question_index: 0,
+            current_question_start_time: None,
+            question_timeout_seconds: 30,
+            status: BattleStatus::Waiting,
+            pot: Amount::ZERO,
+            start_time: None,
+            questions_requested_at: None,
+            question_requests: 0,
+            rules,
+            reveal_start_time: None,
+            reveal_timeout_seconds: 15,
+            sudden_death: vec![],
+            sudden_death_played: false,
+        };
+
+        // Owner joins with real display name; the room is on their own chain, so their funds already are
+        let reward_multiplier = self.state.profile.get().reward_multiplier;
+        let home_chain = self.runtime.chain_id();
+        battle.players.push(PlayerInBattle::new(owner, display_name, reward_multiplier, home_chain));
+
+        self.state.battle.set(battle);
+        self.state.current_room.set(Some(self.runtime.chain_id()));
+
+        self.publish_room_info();
+    }
+
+    // User Chain: send the stake to the play chain's bankroll ahead of the request
+    fn request_join(
+        &mut self,
+        player: AccountOwner,
+        room_chain: ChainId,
+        password: Option<String>,
+        display_name: String,
+        stake: Amount,
+    ) {
+        self.send_funds(player, stake, room_chain);
+
+        let reward_multiplier = self.state.profile.get().reward_multiplier;
+        self.runtime
+            .prepare_message(TriviaMessage::JoinRoomRequest {
+                password,
+                display_name,
+                reward_multiplier,
+                stake,
+            })
+            .with_authentication()
+            .send_to(room_chain);
+    }
+
+    // Moves a player's bankroll balance to another chain; nothing to do if it is already there
+    fn send_funds(&mut self, owner: AccountOwner, amount: Amount, target_chain: ChainId) {
+        if amount.is_zero() || target_chain == self.runtime.chain_id() {
+            return;
+        }
+
+        let bankroll_id = self.runtime.application_parameters().bankroll_app;
+        let send_op = BankrollOperation::SendFunds { owner, amount, target_chain };
+        self.runtime.call_application(true, bankroll_id, &send_op);
+    }
+
+    async fn join_battle(
+        &mut self,
+        player: AccountOwner,
+        home_chain: ChainId,
+        display_name: String,
+        password: Option<String>,
+        reward_multiplier: u32,
+        stake: Amount,
+    ) -> Result<(), String> {
+        let battle = self.state.battle.get_mut();
+
+        if battle.status != BattleStatus::Waiting {
+            return Err("Room is not accepting players".to_string());
+        }
+
+        // A player on another chain must have sent the bet along; LockStakes takes it at start
+        if home_chain != self.runtime.chain_id() && stake != battle.bet_amount {
+            return Err(format!("Room bet is {}", battle.bet_amount));
+        }
+
+        // Password check
+        if let Some(ref pwd) = battle.password {
+            if Some(pwd.as_str()) != password.as_deref() {
+                return Err("Wrong password".to_string());
+            }
+        }
+
+        if battle.players.iter().any(|p| p.owner == player) {
+            return Err("Already in room".to_string());
+        }
+
+        // Room full?
+        if battle.players.len() >= battle.max_players as usize {
+            return Err("Room is full".to_string());
+        }
+
+        battle.players.push(PlayerInBattle::new(player, display_name.clone(), reward_multiplier, home_chain));
+
+        self.publish_room_info();
+
+        self.broadcast(TriviaMessage::PlayerJoined {
+            player,
+            name: display_name,
+        });
+
+        Ok(())
+    }
+
+    fn leave_battle(&mut self, player: AccountOwner) {
+        let battle = self.state.battle.get_mut();
+
+        let idx = match battle.players.iter().position(|p| p.owner == player) {
+            Some(i) => i,
+            None => return,
+        };
+
+        match battle.status {
+            BattleStatus::Waiting => {
+                // Stakes are only locked at StartGame; until then the bet just goes back home
+                let leaving = battle.players.remove(idx);
+                let bet = battle.bet_amount;
+
+                if battle.owner == player {
+                    match battle.players.first() {
+                        Some(next) => battle.owner = next.owner,
+                        None => battle.status = BattleStatus::Finished,
+                    }
+                }
+
+                self.send_funds(player, bet, leaving.home_chain);
+                self.publish_room_info();
+                self.broadcast(TriviaMessage::PlayerLeft { player });
+            }
+
+            BattleStatus::InProgress => {
+                if battle.players[idx].forfeited {
+                    return;
+                }
+                battle.players[idx].forfeited = true;
+
+                let active = battle.players.iter().filter(|p| !p.forfeited).count();
+                let has_questions = !battle.full_questions.is_empty();
+                let awaiting = battle.awaiting_questions();
+
+                self.broadcast(TriviaMessage::PlayerLeft { player });
+
+                // Last one standing takes the pot
+                if active == 1 && has_questions {
+                    self.end_game();
+                } else if active <= 1 {
+                    self.abort_game("Not enough players left".to_string());
+                } else if !awaiting {
+                    // A question master hasn't sent yet can't time out
+                    self.check_question_timeout_and_advance();
+                }
+            }
+
+            BattleStatus::Finished => {}
+        }
+    }
+
+    // Send this play chain's lobby entry to the master chain registry
+    fn publish_room_info(&mut self) {
+        let battle = self.state.battle.get();
+        let room = RoomInfo {
+            id: 0, // Assigned by the master chain
+            chain_id: self.runtime.chain_id(),
+            name: battle.room_name.clone(),
+            current_players: battle.players.len() as u8,
+            max_players: battle.max_players,
+            bet_amount: battle.bet_amount,
+            has_password: battle.password.is_some(),
+            active: battle.status == BattleStatus::Waiting,
+        };
+
+        let master = self.runtime.application_parameters().master_chain;
+        self.send_message(master, TriviaMessage::RoomsData { rooms: vec![room] });
+    }
+
+    // A play chain hosts a single room, so it can only list its own. The room keeps its id
+    // while it stays open; one that no longer takes players is dropped
+    async fn upsert_room(&mut self, origin: ChainId, mut room: RoomInfo) {
+        if !room.active {
+            self.state.rooms.remove(&origin).expect("Failed to remove room");
+            return;
+        }
+
+        room.chain_id = origin;
+        room.id = match self.state.rooms.get(&origin).await.expect("Failed to read rooms") {
+            Some(known) => known.id,
+            None => {
+                let id = *self.state.next_room_id.get();
+                self.state.next_room_id.set(id + 1);
+                id
+            }
+        };
+        self.state.rooms.insert(&origin, room).expect("Failed to store room");
+    }
+
+    // Prefer the fullest matching room so games start sooner
+    async fn find_open_room(&self, min_bet: Amount, max_bet: Amount) -> Option<RoomInfo> {
+        let mut best: Option<RoomInfo> = None;
+        self.state.rooms
+            .for_each_index_value(|_, room| {
+                let fits = room.active
+                    && !room.has_password
+                    && room.current_players < room.max_players
+                    && room.bet_amount >= min_bet
+                    && room.bet_amount <= max_bet;
+                let fuller = best
+                    .as_ref()
+                    .is_none_or(|b| room.current_players > b.current_players);
+                if fits && fuller {
+                    best = Some(room.into_owned());
+                }
+                Ok(())
+            })
+            .await
+            .expect("Failed to read rooms");
+        best
+    }
+
+    fn request_questions(&mut self, count: u8) {
         let now = self.runtime.system_time();
+        let battle = self.state.battle.get_mut();
+        battle.questions_requested_at = Some(now);
+        battle.question_requests += 1;
+        let game_id = battle.game_id;
+        let filter = battle.rules.questions.clone();
+
+        let master_app = self.runtime.application_parameters().master_app;
+        self.runtime.call_application(false, master_app, &MasterOperation::RequestQuestions { game_id, count, filter });
+    }
+
+    // Master never answered RequestQuestions: ask again, then give up
+    fn retry_or_abort_question_request(&mut self) {
+        let now = self.runtime.system_time();
+        let battle = self.state.battle.get();
+
+        let overdue = battle.questions_requested_at.is_some_and(|at| {
+            now.delta_since(at) >= TimeDelta::from_secs(QUESTION_REQUEST_TIMEOUT_SECONDS)
+        });
+        if !overdue {
+            return;
+        }
 
-        let timeout = if let Some(start) = battle.current_question_start_time {
-            (now.microseconds() - start.microseconds()) >= (battle.question_timeout_seconds as i128 * 1_000_000)
+        if battle.question_requests < MAX_QUESTION_REQUESTS {
+            let count = if battle.full_questions.is_empty() { 10 } else { 1 };
+            self.request_questions(count);
+        } else if battle.full_questions.is_empty() {
+            self.abort_game("Question bank did not respond".to_string());
         } else {
-            false
+            // No sudden-death question is coming; settle the tie as it stands
+            self.state.battle.get_mut().sudden_death.clear();
+            self.end_game();
+        }
+    }
+
+    // Ends a game without scoring; every stake goes back to its player
+    fn abort_game(&mut self, reason: String) {
+        let battle = self.state.battle.get_mut();
+        if !battle.pot.is_zero() {
+            let bankroll_id = self.runtime.application_parameters().bankroll_app;
+            let refund_op = BankrollOperation::RefundEscrow { game_id: battle.game_id };
+            self.runtime.call_application(true, bankroll_id, &refund_op);
+
+            // Refunds land on this chain; send them on to the players' own chains
+            let bet = battle.bet_amount;
+            let homes: Vec<(AccountOwner, ChainId)> = battle.players.iter().map(|p| (p.owner, p.home_chain)).collect();
+            for (owner, home_chain) in homes {
+                self.send_funds(owner, bet, home_chain);
+            }
+        }
+        let battle = self.state.battle.get_mut();
+
+        battle.status = BattleStatus::Finished;
+        battle.pot = Amount::ZERO;
+
+        self.broadcast(TriviaMessage::GameAborted { reason });
+    }
+
+    fn check_question_timeout_and_advance(&mut self) {
+        let now = self.runtime.system_time();
+        let battle = self.state.battle.get_mut();
+
+        // The reveal window has its own clock
+        let (phase_start, phase_seconds) = match battle.reveal_start_time {
+            Some(start) => (Some(start), battle.reveal_timeout_seconds),
+            None => (battle.current_question_start_time, battle.question_timeout_seconds),
         };
+        let timeout = phase_start
+            .is_some_and(|start| now.delta_since(start) >= TimeDelta::from_secs(phase_seconds));
+
+        if battle.reveal_start_time.is_some() {
+            let all_revealed = battle
+                .players
+                .iter()
+                .all(|p| p.commitment.is_none() || p.revealed_choice.is_some());
+
+            if all_revealed || timeout {
+                self.score_reveals();
+                self.advance_to_next_question();
+            }
+            return;
+        }
 
-        let all_answered = battle.players.iter().all(|p| p.has_answered_current);
+        let all_answered = battle
+            .players
+            .iter()
+            .all(|p| p.has_answered_current || !battle.is_contender(&p.owner));
+        if !all_answered && !timeout {
+            return;
+        }
 
-        if all_answered || timeout {
+        if battle.rules.answer_mode == AnswerMode::CommitReveal {
+            battle.reveal_start_time = Some(now);
+            let question_index = battle.current_question_index;
+            self.broadcast(TriviaMessage::RevealStarted { question_index });
+        } else {
             self.advance_to_next_question();
         }
     }
 
+    // Commit–reveal: only reveals that matched their commitment are scored,
+    // timed by when the commitment landed
+    fn score_reveals(&mut self) {
+        let battle = self.state.battle.get_mut();
+        let question = &battle.full_questions[battle.current_question_index as usize];
+        let (correct_idx, difficulty) = (question.correct_idx, question.difficulty);
+        let start = battle.current_question_start_time;
+        let window = TimeDelta::from_secs(battle.question_timeout_seconds);
+        let scoring = battle.rules.scoring.clone();
+
+        for p in &mut battle.players {
+            if let (Some(choice), Some(committed_at)) = (p.revealed_choice, p.last_answer_time) {
+                let elapsed = start.map_or(TimeDelta::default(), |start| committed_at.delta_since(start));
+                let points = scoring.points(choice == correct_idx, elapsed, window, difficulty);
+                p.score = p.score.saturating_add(points);
+            }
+        }
+    }
+
     fn advance_to_next_question(&mut self) {
-        let mut battle = self.state.battle.get_mut();
+        let now = self.runtime.system_time();
+        let battle = self.state.battle.get_mut();
+
+        // Cumulative answer time for tie-breaks; a missed question costs the whole window
+        let window = TimeDelta::from_secs(battle.question_timeout_seconds);
+        let start = battle.current_question_start_time;
+        let answer_mode = battle.rules.answer_mode;
+        for p in &mut battle.players {
+            let answered_at = match answer_mode {
+                AnswerMode::Direct => p.last_answer_time,
+                AnswerMode::CommitReveal => p.revealed_choice.and(p.last_answer_time),
+            };
+            let elapsed = match (start, answered_at) {
+                (Some(start), Some(at)) => at.delta_since(start),
+                _ => window,
+            };
+            p.total_answer_micros += elapsed.as_micros();
+        }
+
         battle.current_question_index += 1;
-        battle.current_question_start_time = Some(self.runtime.system_time());
+        battle.current_question_start_time = Some(now);
 
         if battle.current_question_index as usize >= battle.question_ids.len() {
             self.end_game();
             return;
         }
 
+        battle.reveal_start_time = None;
         for p in &mut battle.players {
             p.has_answered_current = false;
             p.last_answer_time = None;
+            p.commitment = None;
+            p.revealed_choice = None;
         }
 
         self.broadcast(TriviaMessage::NextQuestion {
             index: battle.current_question_index,
-            question_id: battle.question_ids[battle.current_question_index as usize],
         });
     }
 
     fn end_game(&mut self) {
-        let mut battle = self.state.battle.get_mut();
-        battle.status = BattleStatus::Finished;
-
-        // Find winner
-        let winner_info = battle.players.iter()
-            .max_by_key(|p| p.score)
-            .expect("No players");
+        let battle = self.state.battle.get_mut();
+        let tie_break = battle.rules.tie_break;
+
+        // Rank players still in the game, best first
+        let mut ranking: Vec<PlayerInBattle> = battle.players.iter()
+            .filter(|p| !p.forfeited)
+            .cloned()
+            .collect();
+        ranking.sort_by(|a, b| tie_break.compare(a, b));
+
+        let leader = ranking.first().expect("No players");
+        let winners: Vec<PlayerInBattle> = ranking.iter()
+            .take_while(|p| tie_break.compare(leader, p) == Ordering::Equal)
+            .cloned()
+            .collect();
+
+        // Tied at the top: play one extra question between them
+        if winners.len() > 1 && tie_break == TieBreak::SuddenDeath && !battle.sudden_death_played {
+            let players: Vec<AccountOwner> = winners.iter().map(|p| p.owner).collect();
+            battle.sudden_death = players.clone();
+            battle.sudden_death_played = true;
+            battle.question_requests = 0;
+            battle.reveal_start_time = None;
+            for p in &mut battle.players {
+                p.has_answered_current = false;
+                p.last_answer_time = None;
+                p.commitment = None;
+                p.revealed_choice = None;
+            }
 
-        let winner = winner_info.owner;
-        let winner_score = winner_info.score;
-        let winner_name = winner_info.name.clone();
+            self.broadcast(TriviaMessage::SuddenDeath { players });
+            self.request_questions(1);
+            return;
+        }
 
-        // Calculate payouts
-        let fee = battle.pot / 20; // 5%
-        let base_payout = battle.pot.saturating_sub(fee);
+        battle.status = BattleStatus::Finished;
+        battle.sudden_death.clear();
+
+        // Calculate payouts from the room's payout table; rounding dust goes to the fee
+        let prize = battle.pot.saturating_sub(battle.pot.saturating_div(20)); // 5% fee
+        let payouts = battle.rules.payout.split(prize, &ranking, tie_break);
+        let paid = payouts.iter().fold(Amount::ZERO, |sum, p| sum.saturating_add(p.amount));
+        let fee = battle.pot.saturating_sub(paid);
+        let pot = battle.pot;
+        let game_id = battle.game_id;
+        let room_name = battle.room_name.clone();
+
+        // Disburse the escrow: shares to the payees, the fee to master
+        let bankroll_id = self.runtime.application_parameters().bankroll_app;
+        if !battle.pot.is_zero() {
+            let release_op = BankrollOperation::ReleaseEscrow {
+                game_id,
+                payouts: payouts.iter().filter(|p| !p.amount.is_zero()).cloned().collect(),
+                fee,
+            };
+            self.runtime.call_application(true, bankroll_id, &release_op);
+        }
 
-        // === TIER MULTIPLIER (SIMPLIFIED) ===
-        let multiplier = self.get_tier_multiplier(winner_score);
-        let final_payout = base_payout * multiplier / 100;
+        // Winnings land on this chain; send them on to the players' own chains. Tier bonuses
+        // come out of master's bankroll reserve and reach the players separately.
+        for payout in &payouts {
+            let (home_chain, multiplier) = ranking.iter()
+                .find(|p| p.owner == payout.player)
+                .map_or((self.runtime.chain_id(), 100), |p| (p.home_chain, p.reward_multiplier));
+            self.send_funds(payout.player, payout.amount, home_chain);
+
+            let bonus = payout.amount
+                .saturating_mul(multiplier.saturating_sub(100) as u128)
+                .saturating_div(100);
+            if !bonus.is_zero() {
+                let bonus_op = BankrollOperation::PayBonus {
+                    owner: payout.player,
+                    amount: bonus,
+                    target_chain: home_chain,
+                };
+                self.runtime.call_application(true, bankroll_id, &bonus_op);
+            }
+        }
 
         // === UPDATE GLOBAL LEADERBOARD ===
         let mut entries = self.state.leaderboard.get_mut().clone();
-        if let Some(entry) = entries.iter_mut().find(|e| e.player == winner) {
-            entry.wins += 1;
-            entry.total_score += winner_score as u64;
-            entry.lifetime_winnings.saturating_add_assign(final_payout);
-        } else {
-            entries.push(LeaderboardEntry {
-                player: winner,
-                name: winner_name,
-                wins: 1,
-                total_score: winner_score as u64,
-                lifetime_winnings: final_payout,
-            });
+        for payout in &payouts {
+            let player = ranking.iter().find(|p| p.owner == payout.player).expect("Payee is ranked");
+            let won = u64::from(winners.iter().any(|w| w.owner == player.owner));
+
+            if let Some(entry) = entries.iter_mut().find(|e| e.player == player.owner) {
+                entry.wins += won;
+                entry.total_score += player.score;
+                entry.lifetime_winnings.saturating_add_assign(payout.amount);
+            } else {
+                entries.push(LeaderboardEntry {
+                    player: player.owner,
+                    name: player.name.clone(),
+                    wins: won,
+                    total_score: player.score,
+                    lifetime_winnings: payout.amount,
+                });
+            }
         }
 
         // Sort and keep top 100
@@ -324,32 +1063,46 @@ impl TriviaContract {
         }
         self.state.leaderboard.set(entries);
 
-        self.broadcast(TriviaMessage::GameEnded { winner, payout: final_payout });
-
-        // Send fee to Master
-        if !fee.is_zero() {
-            let master = self.runtime.application_parameters().master_chain;
-            self.send_message(master, TriviaMessage::SendProtocolFee { amount: fee });
+        // Profiles, and the multipliers that come with them, live on the players' own chains
+        for player in &ranking {
+            let winnings = payouts.iter()
+                .find(|p| p.player == player.owner)
+                .map_or(Amount::ZERO, |p| p.amount);
+            let won = winners.iter().any(|w| w.owner == player.owner);
+            self.send_message(player.home_chain, TriviaMessage::GameFinished {
+                score: player.score,
+                won,
+                winnings,
+                pot,
+            });
         }
 
-        // Payout winner
-        if !final_payout.is_zero() {
-            let bankroll_id = self.runtime.application_parameters().bankroll_app;
-            let payout_op = BankrollOperation::UpdateBalance {
-                owner: winner,
-                amount: final_payout,
-            };
-            self.runtime.call_application(true, bankroll_id, &payout_op);
-        }
+        let ranking: Vec<PlayerScore> = ranking.iter()
+            .map(|p| PlayerScore {
+                player: p.owner,
+                name: p.name.clone(),
+                score: p.score,
+            })
+            .collect();
+
+        let result = GameResult {
+            game_id,
+            room_name,
+            finished_at: self.runtime.system_time(),
+            ranking: ranking.clone(),
+            payouts: payouts.clone(),
+            fee,
+        };
+        self.state.results.insert(&game_id, result).expect("Failed to store result");
 
-        battle.pot = Amount::ZERO;
-    }
+        self.broadcast(TriviaMessage::GameEnded { ranking, payouts });
+
+        // Let master know about its fee; bankroll has already forwarded the tokens
+        if !fee.is_zero() {
+            let master_app = self.runtime.application_parameters().master_app;
+            self.runtime.call_application(false, master_app, &MasterOperation::SendProtocolFee { amount: fee });
+        }
 
-    fn get_tier_multiplier(&self, score: u64) -> u32 {
-        // Simple tier based on total score across games
-        if score >= 1000 { 200 }      // Diamond 2x
-        else if score >= 500 { 150 }  // Gold 1.5x
-        else if score >= 200 { 125 }  // Silver 1.25x
-        else { 100 }                  // Bronze 1x
+        self.state.battle.get_mut().pot = Amount::ZERO;
     }
 }
\ No newline at end of file
@@ -1,9 +1,10 @@
-use linera_sdk::linera_base_types::{AccountOwner, Amount, Timestamp};
+use linera_sdk::linera_base_types::{AccountOwner, Amount, ChainId, CryptoHash, TimeDelta, Timestamp};
 use serde::{Deserialize, Serialize};
-use async_graphql::SimpleObject;
+use async_graphql::{Enum, SimpleObject};
 
 #[derive(Debug, Clone, Serialize, Deserialize, SimpleObject)]
 pub struct TriviaBattle {
+    pub game_id: u64,
     pub room_name: String,
     pub owner: AccountOwner,
     pub max_players: u8,
@@ -13,12 +14,34 @@ pub struct TriviaBattle {
     pub players: Vec<PlayerInBattle>,
     pub question_ids: Vec<u64>,
     pub full_questions: Vec<Question>, // From master
+    pub questions_matched: u32, // How many of them fit the room's question filter
     pub current_question_index: u8,
 
     pub status: BattleStatus,
     pub pot: Amount,
     pub start_time: Option<Timestamp>,
+    pub questions_requested_at: Option<Timestamp>,
+    pub question_requests: u8,
+    pub current_question_start_time: Option<Timestamp>,
     pub question_timeout_seconds: u64,  // 30
+
+    pub rules: RoomRules,
+    pub reveal_start_time: Option<Timestamp>, // Set while a commit–reveal question is revealing
+    pub reveal_timeout_seconds: u64,  // 15
+
+    pub sudden_death: Vec<AccountOwner>, // Tied players playing the extra question
+    pub sudden_death_played: bool,
+}
+
+/// Final standings of a finished game, kept for the service
+#[derive(Debug, Clone, Serialize, Deserialize, SimpleObject)]
+pub struct GameResult {
+    pub game_id: u64,
+    pub room_name: String,
+    pub finished_at: Timestamp,
+    pub ranking: Vec<PlayerScore>,
+    pub payouts: Vec<Payout>,
+    pub fee: Amount,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, SimpleObject)]
@@ -28,13 +51,324 @@ pub struct PlayerInBattle {
     pub score: u64,
     pub has_answered_current: bool,
     pub last_answer_time: Option<Timestamp>,
+    pub commitment: Option<CryptoHash>,
+    pub revealed_choice: Option<u8>,
+    pub forfeited: bool, // Left mid-game; stake stays in the pot
+    pub total_answer_micros: u64, // Used for tie-breaks
+    pub reward_multiplier: u32, // 100 = 1x; bonus above 1x comes from the bankroll reserve
+    pub home_chain: ChainId, // Where the player's funds live; stakes and winnings go back there
+}
+
+impl PlayerInBattle {
+    pub fn new(owner: AccountOwner, name: String, reward_multiplier: u32, home_chain: ChainId) -> Self {
+        Self {
+            owner,
+            name,
+            score: 0,
+            has_answered_current: false,
+            last_answer_time: None,
+            commitment: None,
+            revealed_choice: None,
+            forfeited: false,
+            total_answer_micros: 0,
+            reward_multiplier: reward_multiplier.max(100),
+            home_chain,
+        }
+    }
+}
+
+/// What the service exposes while a battle runs: no password, no open answers
+#[derive(Debug, Clone, Serialize, Deserialize, SimpleObject)]
+pub struct TriviaBattleView {
+    pub room_name: String,
+    pub owner: AccountOwner,
+    pub max_players: u8,
+    pub bet_amount: Amount,
+    pub has_password: bool,
+
+    pub players: Vec<PlayerInBattle>,
+    pub question_ids: Vec<u64>, // Closed questions only; master would give away the open one's answer
+    pub questions: Vec<QuestionView>, // Closed questions + the current one
+    pub questions_matched: u32,
+    pub current_question_index: u8,
+
+    pub status: BattleStatus,
+    pub pot: Amount,
+    pub start_time: Option<Timestamp>,
+    pub question_timeout_seconds: u64,
+    pub rules: RoomRules,
+    pub revealing: bool,
+    pub sudden_death: Vec<AccountOwner>,
+}
+
+/// A question as seen by players; `id` and `correct_idx` are only set once it has closed
+#[derive(Debug, Clone, Serialize, Deserialize, SimpleObject)]
+pub struct QuestionView {
+    pub id: Option<u64>,
+    pub text: String,
+    pub choices: Vec<String>,
+    pub category: String,
+    pub difficulty: u8,
+    pub correct_idx: Option<u8>,
+}
+
+impl TriviaBattle {
+    pub fn redacted(&self) -> TriviaBattleView {
+        let current = self.current_question_index as usize;
+        let finished = self.status == BattleStatus::Finished;
+        let closed = |i: usize| finished || i < current;
+
+        let questions = self
+            .full_questions
+            .iter()
+            .enumerate()
+            .filter(|(i, _)| finished || *i <= current)
+            .map(|(i, q)| QuestionView {
+                id: closed(i).then_some(q.id),
+                text: q.text.clone(),
+                choices: q.choices.clone(),
+                category: q.category.clone(),
+                difficulty: q.difficulty,
+                correct_idx: closed(i).then_some(q.correct_idx),
+            })
+            .collect();
+
+        TriviaBattleView {
+            room_name: self.room_name.clone(),
+            owner: self.owner,
+            max_players: self.max_players,
+            bet_amount: self.bet_amount,
+            has_password: self.password.is_some(),
+            players: self.players.clone(),
+            question_ids: self.question_ids.iter().enumerate()
+                .filter(|(i, _)| closed(*i))
+                .map(|(_, id)| *id)
+                .collect(),
+            questions,
+            questions_matched: self.questions_matched,
+            current_question_index: self.current_question_index,
+            status: self.status,
+            pot: self.pot,
+            start_time: self.start_time,
+            question_timeout_seconds: self.question_timeout_seconds,
+            rules: self.rules.clone(),
+            revealing: self.reveal_start_time.is_some(),
+            sudden_death: self.sudden_death.clone(),
+        }
+    }
+}
+
+impl TriviaBattle {
+    /// Questions ran out and more were requested from master
+    pub fn awaiting_questions(&self) -> bool {
+        self.current_question_index as usize >= self.full_questions.len()
+    }
+
+    /// Still in the game and, during sudden death, one of the tied players
+    pub fn is_contender(&self, owner: &AccountOwner) -> bool {
+        let active = self.players.iter().any(|p| p.owner == *owner && !p.forfeited);
+        active && (self.sudden_death.is_empty() || self.sudden_death.contains(owner))
+    }
+}
+
+impl TieBreak {
+    /// Orders players best first; `Equal` means they are still tied
+    pub fn compare(&self, a: &PlayerInBattle, b: &PlayerInBattle) -> Ordering {
+        let by_score = b.score.cmp(&a.score);
+        match self {
+            TieBreak::SplitPot => by_score,
+            TieBreak::AnswerTime | TieBreak::SuddenDeath => {
+                by_score.then(a.total_answer_micros.cmp(&b.total_answer_micros))
+            }
+        }
+    }
 }
 
-#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, SimpleObject)]
+impl PayoutRules {
+    pub fn is_valid(&self) -> bool {
+        match self.kind {
+            PayoutKind::Table => {
+                !self.table.is_empty() && self.table.iter().map(|&share| share as u64).sum::<u64>() == 100
+            }
+            PayoutKind::WinnerTakesAll | PayoutKind::Proportional => true,
+        }
+    }
+
+    /// Splits `prize` over a best-first ranking. Tied players pool and share
+    /// their places; places nobody reached are dropped and the rest rescaled.
+    pub fn split(&self, prize: Amount, ranking: &[PlayerInBattle], tie_break: TieBreak) -> Vec<Payout> {
+        let shares: Vec<u128> = match self.kind {
+            PayoutKind::WinnerTakesAll => vec![1],
+            PayoutKind::Table => self.table.iter().map(|&pct| pct as u128).collect(),
+            PayoutKind::Proportional if ranking.iter().all(|p| p.score == 0) => {
+                vec![1; ranking.len()]
+            }
+            PayoutKind::Proportional => ranking.iter().map(|p| p.score as u128).collect(),
+        };
+        let shares = &shares[..shares.len().min(ranking.len())];
+        let total: u128 = shares.iter().sum();
+        if total == 0 {
+            return vec![];
+        }
+
+        let mut payouts = Vec::new();
+        let mut start = 0;
+        while start < ranking.len() {
+            let tied = ranking[start..]
+                .iter()
+                .take_while(|p| tie_break.compare(&ranking[start], p) == Ordering::Equal)
+                .count();
+            let end = start + tied;
+
+            let group_share: u128 = shares.iter().take(end).skip(start).sum();
+            let each = prize
+                .saturating_mul(group_share)
+                .saturating_div(total)
+                .saturating_div(tied as u128);
+
+            if !each.is_zero() {
+                for p in &ranking[start..end] {
+                    payouts.push(Payout { player: p.owner, amount: each });
+                }
+            }
+            start = end;
+        }
+        payouts
+    }
+}
+
+impl ScoringRules {
+    /// Largest `base_points` or `max_speed_bonus` a room may set
+    pub const MAX_POINTS: u64 = 1_000_000;
+
+    pub fn is_valid(&self) -> bool {
+        self.base_points <= Self::MAX_POINTS && self.max_speed_bonus <= Self::MAX_POINTS
+    }
+
+    /// Wrong answers score nothing; the speed bonus only rewards correct ones
+    pub fn points(&self, is_correct: bool, elapsed: TimeDelta, window: TimeDelta, difficulty: u8) -> u64 {
+        if !is_correct {
+            return 0;
+        }
+
+        let window = window.as_micros().max(1) as u128;
+        let remaining = window.saturating_sub(elapsed.as_micros() as u128);
+        let max_bonus = self.max_speed_bonus as u128;
+
+        let speed_bonus = match self.decay {
+            SpeedDecay::Linear => max_bonus * remaining / window,
+            SpeedDecay::Quadratic => max_bonus * remaining * remaining / (window * window),
+            SpeedDecay::Off => 0,
+        };
+
+        let weight = difficulty.clamp(1, 5) as u64;
+        self.base_points.saturating_add(speed_bonus as u64).saturating_mul(weight)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Enum)]
 pub enum BattleStatus {
     Waiting,
     InProgress,
     Finished,
 }
 
-use abi::trivia::Question; // Make sure Question is in abi
\ No newline at end of file
+use abi::trivia::{Payout, PlayerScore, Question}; // Make sure Question is in abi
+use crate::{PayoutKind, PayoutRules, RoomRules, ScoringRules, SpeedDecay, TieBreak};
+use std::cmp::Ordering;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn player(n: u8, score: u64, total_answer_micros: u64) -> PlayerInBattle {
+        let home_chain = ChainId(CryptoHash::from([n; 32]));
+        let mut player = PlayerInBattle::new(AccountOwner::Address20([n; 20]), format!("player {n}"), 100, home_chain);
+        player.score = score;
+        player.total_answer_micros = total_answer_micros;
+        player
+    }
+
+    fn rules(kind: PayoutKind, table: &[u32]) -> PayoutRules {
+        PayoutRules { kind, table: table.to_vec() }
+    }
+
+    fn amounts(payouts: &[Payout]) -> Vec<(AccountOwner, Amount)> {
+        payouts.iter().map(|p| (p.player, p.amount)).collect()
+    }
+
+    fn tokens(n: u128) -> Amount {
+        Amount::from_tokens(n)
+    }
+
+    #[test]
+    fn winner_takes_all() {
+        let ranking = [player(1, 30, 0), player(2, 20, 0), player(3, 10, 0)];
+        let payouts = rules(PayoutKind::WinnerTakesAll, &[]).split(tokens(90), &ranking, TieBreak::AnswerTime);
+
+        assert_eq!(amounts(&payouts), [(ranking[0].owner, tokens(90))]);
+    }
+
+    #[test]
+    fn tied_winners_share_or_are_split_by_answer_time() {
+        let ranking = [player(1, 30, 500), player(2, 30, 900), player(3, 10, 0)];
+        let winner_takes_all = rules(PayoutKind::WinnerTakesAll, &[]);
+
+        let payouts = winner_takes_all.split(tokens(90), &ranking, TieBreak::SplitPot);
+        assert_eq!(amounts(&payouts), [(ranking[0].owner, tokens(45)), (ranking[1].owner, tokens(45))]);
+
+        let payouts = winner_takes_all.split(tokens(90), &ranking, TieBreak::AnswerTime);
+        assert_eq!(amounts(&payouts), [(ranking[0].owner, tokens(90))]);
+    }
+
+    #[test]
+    fn table_pools_tied_places() {
+        let ranking = [player(1, 30, 0), player(2, 20, 0), player(3, 20, 0)];
+        let payouts = rules(PayoutKind::Table, &[60, 30, 10]).split(tokens(100), &ranking, TieBreak::SplitPot);
+
+        assert_eq!(
+            amounts(&payouts),
+            [(ranking[0].owner, tokens(60)), (ranking[1].owner, tokens(20)), (ranking[2].owner, tokens(20))],
+        );
+    }
+
+    #[test]
+    fn table_rescales_places_nobody_reached() {
+        let ranking = [player(1, 30, 0), player(2, 20, 0)];
+        let payouts = rules(PayoutKind::Table, &[60, 30, 10]).split(tokens(90), &ranking, TieBreak::AnswerTime);
+
+        assert_eq!(amounts(&payouts), [(ranking[0].owner, tokens(60)), (ranking[1].owner, tokens(30))]);
+    }
+
+    #[test]
+    fn proportional_follows_scores_and_splits_evenly_when_nobody_scored() {
+        let proportional = rules(PayoutKind::Proportional, &[]);
+
+        let ranking = [player(1, 30, 0), player(2, 10, 0), player(3, 0, 0)];
+        let payouts = proportional.split(tokens(80), &ranking, TieBreak::AnswerTime);
+        assert_eq!(amounts(&payouts), [(ranking[0].owner, tokens(60)), (ranking[1].owner, tokens(20))]);
+
+        let ranking = [player(1, 0, 0), player(2, 0, 0)];
+        let payouts = proportional.split(tokens(80), &ranking, TieBreak::SplitPot);
+        assert_eq!(amounts(&payouts), [(ranking[0].owner, tokens(40)), (ranking[1].owner, tokens(40))]);
+    }
+
+    #[test]
+    fn payouts_never_exceed_the_prize() {
+        let ranking = [player(1, 7, 0), player(2, 5, 0), player(3, 3, 0)];
+        for payout in [rules(PayoutKind::Proportional, &[]), rules(PayoutKind::Table, &[50, 33, 17])] {
+            let paid = payout
+                .split(tokens(1), &ranking, TieBreak::AnswerTime)
+                .iter()
+                .fold(Amount::ZERO, |sum, p| sum.saturating_add(p.amount));
+            assert!(paid <= tokens(1), "{:?} paid {paid}", payout.kind);
+        }
+    }
+
+    #[test]
+    fn tables_must_add_up_to_a_hundred_without_wrapping() {
+        assert!(rules(PayoutKind::Table, &[60, 30, 10]).is_valid());
+        assert!(!rules(PayoutKind::Table, &[60, 30]).is_valid());
+        assert!(!rules(PayoutKind::Table, &[u32::MAX, 101]).is_valid());
+    }
+}
@@ -1,7 +1,10 @@
+pub mod game;
+
 use async_graphql::{Request, Response};
 use bankroll::BankrollAbi;
+use async_graphql::{Enum, InputObject, SimpleObject};
 use linera_sdk::linera_base_types::{
-    AccountOwner, Amount, ApplicationId, ChainId, Timestamp,
+    AccountOwner, Amount, ApplicationId, BcsHashable, ChainId, CryptoHash, Timestamp,
 };
 use linera_sdk::{
     graphql::GraphQLMutationRoot,
@@ -11,7 +14,8 @@ use serde::{Deserialize, Serialize};
 
 // Shared ABI types
 use abi::management::{PublicChainInfo, RoomInfo};
-use abi::trivia::{Question, QuestionInput, TriviaGame}; // Added QuestionInput
+use abi::master::MasterAbi;
+use abi::trivia::{Payout, PlayerScore, Question, QuestionFilter, QuestionInput, TriviaGame};
 
 #[derive(Debug, Deserialize, Serialize)]
 pub struct TriviaAbi;
@@ -26,51 +30,97 @@ impl ServiceAbi for TriviaAbi {
     type QueryResponse = Response;
 }
 
-#[derive(Debug, Deserialize, Serialize, GraphQLMutationRoot)]
-pub enum TriviaOperation {
-    // User actions
-    InitialSetup {},
-    FindPlayChain {}, // Matchmaking
-    OpenRoom {
-        name: String,
-        max_players: u8,
-        bet_amount: Amount,
-        password: Option<String>,
-        display_name: String,
-    },
-    RequestJoinRoom {
-        room_chain: ChainId,
-        password: Option<String>,
-        display_name: String,
-    },
-    StartGame {},
-    Answer {
-        question_index: u8,
-        choice: u8,
-    },
-    LeaveRoom {},
+pub use operation::TriviaOperation;
 
-    // Admin (Master only)
-    AddPublicChain {
-        chain_id: ChainId,
-        initial_funding: Amount,
-    },
-    AddRoomManagerChain {
-        chain_id: ChainId,
-    },
-    AddQuestions {
-        questions: Vec<QuestionInput>, // Changed to QuestionInput
-    },
+// GraphQLMutationRoot turns each OpenRoom field into an argument of a generated
+// resolver outside the enum, so the allow covers this module instead of the crate
+#[allow(clippy::too_many_arguments)]
+mod operation {
+    use super::*;
+
+    #[derive(Debug, Deserialize, Serialize, GraphQLMutationRoot)]
+    pub enum TriviaOperation {
+        // User actions
+        InitialSetup {},
+        FindPlayChain {
+            display_name: String,
+            min_bet: Amount,
+            max_bet: Amount,
+        }, // Matchmaking
+        OpenRoom {
+            name: String,
+            max_players: u8,
+            bet_amount: Amount,
+            password: Option<String>,
+            display_name: String,
+            rules: RoomRules,
+        },
+        RequestJoinRoom {
+            room_chain: ChainId,
+            password: Option<String>,
+            display_name: String,
+            stake: Amount, // The room's bet, sent ahead to its chain; returned if the join fails
+        },
+        StartGame {},
+        Answer {
+            question_index: u8,
+            choice: u8,
+        },
+        CommitAnswer {
+            question_index: u8,
+            commitment: CryptoHash, // CryptoHash::new(&AnswerReveal { .. })
+        },
+        RevealAnswer {
+            question_index: u8,
+            choice: u8,
+            salt: String,
+        },
+        AdvanceQuestion {}, // Anyone: closes a question once its deadline has passed
+        LeaveRoom {},
+
+        // Sends a question to the master moderation queue
+        SubmitQuestion {
+            question: QuestionInput,
+        },
+        // Called by the master app on this chain when one of our submissions made it into the bank
+        QuestionApproved {
+            submission_id: u64,
+            question_id: u64,
+        },
+        // Called by the master app on this chain with the questions a game asked for
+        ReceiveQuestions {
+            game_id: u64,
+            question_ids: Vec<u64>,
+            questions: Vec<Question>,
+            matched: u32, // How many fit the filter; the rest are the closest master had
+        },
+
+        // Admin (Master only)
+        AddPublicChain {
+            chain_id: ChainId,
+            initial_funding: Amount,
+        },
+        AddRoomManagerChain {
+            chain_id: ChainId,
+        },
+    }
 }
 
 #[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
 pub enum TriviaMessage {
     GameUpdate { game: TriviaGame },
+    JoinRoomRequest {
+        password: Option<String>,
+        display_name: String,
+        reward_multiplier: u32, // From the player's profile on their user chain
+        stake: Amount, // Already credited to the player by the bankroll on the play chain
+    },
     JoinResult {
         success: bool,
         message: String,
         room_chain: Option<ChainId>,
     },
+    LeaveRoomRequest,
     PublicChainsData { chains: Vec<PublicChainInfo> },
     RoomsData { rooms: Vec<RoomInfo> },
 
@@ -82,20 +132,147 @@ pub enum TriviaMessage {
         choice: u8,
         answered_at: Timestamp,
     },
-    GameStarted { question_ids: Vec<u64> },
-    NextQuestion { index: u8, question_id: u64 },
-    GameEnded { winner: AccountOwner, payout: Amount },
+    PlayerCommitted {
+        player: AccountOwner,
+        question_index: u8,
+        committed_at: Timestamp,
+    },
+    RevealStarted { question_index: u8 },
+    // No question IDs until a question closes: master would give away the answer
+    GameStarted { question_count: u8 },
+    NextQuestion { index: u8 },
+    SuddenDeath { players: Vec<AccountOwner> },
+    GameEnded { ranking: Vec<PlayerScore>, payouts: Vec<Payout> },
+    // To each player's own chain, where their profile and tier multiplier live.
+    // pot is what was staked in total; games played for nothing don't count towards tiers
+    GameFinished { score: u64, won: bool, winnings: Amount, pot: Amount },
+    GameAborted { reason: String },
+
+    // Between master instances: from the play chain, and the questions back to it
+    RequestQuestions { game_id: u64, count: u8, filter: QuestionFilter, trivia_app: ApplicationId },
+    ReceiveQuestions {
+        game_id: u64,
+        question_ids: Vec<u64>,
+        questions: Vec<Question>,
+        matched: u32,
+        trivia_app: ApplicationId,
+    },
+
+    FindPlayChainRequest {
+        player: AccountOwner,
+        display_name: String,
+        min_bet: Amount,
+        max_bet: Amount,
+    },
+    FindPlayChainResult {
+        room_chain: Option<ChainId>,
+        display_name: String,
+        bet_amount: Amount,
+    },
+
+    SendProtocolFee { amount: Amount }, // Between master instances, from the play chain
+
+    // Between master instances: from the player's chain, and the approval back to it
+    SubmitQuestion { question: QuestionInput, trivia_app: ApplicationId },
+    QuestionApproved { submission_id: u64, question_id: u64, trivia_app: ApplicationId },
+}
+
+/// Per-room gameplay rules, chosen at `OpenRoom`
+#[derive(Clone, Debug, Default, Deserialize, Eq, PartialEq, Serialize, SimpleObject, InputObject)]
+#[graphql(input_name = "RoomRulesInput")]
+pub struct RoomRules {
+    pub answer_mode: AnswerMode,
+    pub scoring: ScoringRules,
+    pub tie_break: TieBreak,
+    pub payout: PayoutRules,
+    pub questions: QuestionFilter,
+}
+
+#[derive(Clone, Copy, Debug, Default, Deserialize, Enum, Eq, PartialEq, Serialize)]
+pub enum AnswerMode {
+    /// Answers are submitted and scored in the clear
+    #[default]
+    Direct,
+    /// Players commit a hash while the question is open and reveal after it closes
+    CommitReveal,
+}
+
+/// Points for a correct answer: `(base_points + speed bonus) * difficulty`
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize, SimpleObject, InputObject)]
+#[graphql(input_name = "ScoringRulesInput")]
+pub struct ScoringRules {
+    pub base_points: u64,
+    /// Bonus for answering instantly; decays to zero at the question timeout
+    pub max_speed_bonus: u64,
+    pub decay: SpeedDecay,
+}
 
-    RequestQuestions { count: u8 },
-    ReceiveQuestions { question_ids: Vec<u64>, questions: Vec<Question> },
+impl Default for ScoringRules {
+    fn default() -> Self {
+        Self {
+            base_points: 100,
+            max_speed_bonus: 50,
+            decay: SpeedDecay::Linear,
+        }
+    }
+}
 
-    FindPlayChainRequest { player: AccountOwner },
+#[derive(Clone, Copy, Debug, Default, Deserialize, Enum, Eq, PartialEq, Serialize)]
+pub enum SpeedDecay {
+    /// Bonus falls evenly over the question window
+    #[default]
+    Linear,
+    /// Bonus falls off quickly after the first seconds
+    Quadratic,
+    /// No speed bonus
+    Off,
+}
+
+/// How players level on points are separated at the end of a game
+#[derive(Clone, Copy, Debug, Default, Deserialize, Enum, Eq, PartialEq, Serialize)]
+pub enum TieBreak {
+    /// Tied players split the pot evenly
+    SplitPot,
+    /// Lowest cumulative answer time wins; still tied players split the pot
+    #[default]
+    AnswerTime,
+    /// Answer time first, then one extra question from master; split if still tied
+    SuddenDeath,
+}
 
-    SendProtocolFee { amount: Amount },
+/// How the prize (pot minus fee) is shared out at the end of a game
+#[derive(Clone, Debug, Default, Deserialize, Eq, PartialEq, Serialize, SimpleObject, InputObject)]
+#[graphql(input_name = "PayoutRulesInput")]
+pub struct PayoutRules {
+    pub kind: PayoutKind,
+    /// Percentages by place for `Table`, e.g. `[60, 30, 10]`; must add up to 100
+    pub table: Vec<u32>,
 }
 
+#[derive(Clone, Copy, Debug, Default, Deserialize, Enum, Eq, PartialEq, Serialize)]
+pub enum PayoutKind {
+    #[default]
+    WinnerTakesAll,
+    /// Fixed percentages for the top places
+    Table,
+    /// Each player gets a share proportional to their score
+    Proportional,
+}
+
+/// Preimage of a commit–reveal answer; the commitment is `CryptoHash::new(&reveal)`
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct AnswerReveal {
+    pub player: AccountOwner,
+    pub question_index: u8,
+    pub choice: u8,
+    pub salt: String,
+}
+
+impl BcsHashable<'_> for AnswerReveal {}
+
 #[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct TriviaParameters {
     pub master_chain: ChainId,
     pub bankroll_app: ApplicationId<BankrollAbi>,
+    pub master_app: ApplicationId<MasterAbi>,
 }
\ No newline at end of file
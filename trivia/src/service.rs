@@ -13,7 +13,7 @@ use linera_sdk::{
     Service, ServiceRuntime,
 };
 use state::TriviaState;
-use game::{TriviaBattle, PlayerInBattle};
+use game::{GameResult, TriviaBattleView};
 
 // === ADD THESE LINES HERE ===
 use abi::management::{PublicChainInfo, RoomInfo};
@@ -67,9 +67,25 @@ struct QueryRoot {
 
 #[Object]
 impl QueryRoot {
-    // Get current battle (for Play Chain)
-    async fn current_battle(&self) -> TriviaBattle {
-        self.state.battle.get().clone()
+    // Get current battle (for Play Chain), with unanswered questions redacted
+    async fn current_battle(&self) -> TriviaBattleView {
+        self.state.battle.get().redacted()
+    }
+
+    // Finished games on this play chain, oldest first
+    async fn game_results(&self) -> Vec<GameResult> {
+        let mut results = Vec::new();
+        let keys = self.state.results.indices().await.unwrap_or_default();
+        for key in keys {
+            if let Some(result) = self.state.results.get(&key).await.unwrap_or(None) {
+                results.push(result);
+            }
+        }
+        results
+    }
+
+    async fn game_result(&self, game_id: u64) -> Option<GameResult> {
+        self.state.results.get(&game_id).await.unwrap_or(None)
     }
 
     // Get player's current room (for User Chain)
@@ -78,7 +94,7 @@ impl QueryRoot {
     }
 
     // Get profile & stats
-    async fn my_profile(&self) -> abi::bet_chip_profile::Profile {
+    async fn my_profile(&self) -> abi::player_profile::PlayerProfile {
         self.state.profile.get().clone()
     }
 
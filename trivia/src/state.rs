@@ -1,5 +1,5 @@
-use crate::game::{TriviaBattle, PlayerInBattle, BattleStatus};
-use abi::bet_chip_profile::Profile;
+use crate::game::{GameResult, TriviaBattle, PlayerInBattle, BattleStatus};
+use abi::player_profile::PlayerProfile;
 use abi::leaderboard::SimpleLeaderboardEntry;
 use abi::management::{PublicChainInfo, RoomInfo};
 use abi::trivia::UserStatus;
@@ -11,16 +11,20 @@ use linera_sdk::views::{linera_views, MapView, RegisterView, RootView, ViewStora
 pub struct TriviaState {
     // Global
     pub public_chains: MapView<ChainId, PublicChainInfo>,
-    pub rooms: MapView<u64, RoomInfo>,
+    // Open rooms, by the play chain hosting them; a room is dropped once it stops taking players
+    pub rooms: MapView<ChainId, RoomInfo>,
+    pub next_room_id: RegisterView<u64>,
     pub leaderboard: RegisterView<Vec<SimpleLeaderboardEntry>>,
 
     // User Chain
-    pub profile: RegisterView<Profile>,
+    pub profile: RegisterView<PlayerProfile>,
     pub user_status: RegisterView<UserStatus>,
     pub current_room: RegisterView<Option<ChainId>>,
 
     // Play Chain (this is where the battle happens)
     pub battle: RegisterView<TriviaBattle>,
+    pub next_game_id: RegisterView<u64>,
+    pub results: MapView<u64, GameResult>,
 
     // Public Chain (lobby)
     pub public_info: RegisterView<PublicChainInfo>,